@@ -26,6 +26,9 @@ struct Opts {
     /// Ouput file, if no file is provided results will be written to stdout
     #[clap(short, long)]
     output_file: Option<std::path::PathBuf>,
+    /// Kind of bindings to generate, either blocking functions for postgres or async functions for tokio-postgres
+    #[clap(long, default_value = "sync", possible_values = &["sync", "async"])]
+    flavor: postgres_bindgen::Flavor,
 }
 
 fn main() -> std::io::Result<()> {
@@ -46,8 +49,11 @@ fn main() -> std::io::Result<()> {
         conn_config.dbname(&opts.dbname);
     }
 
+    // Read the generator options
+    let options = postgres_bindgen::Options { flavor: opts.flavor };
+
     // Run the transformation
-    let code = postgres_bindgen::run(&conn_config).to_string();
+    let code = postgres_bindgen::run(&conn_config, &options).to_string();
 
     // Write the result
     if let Some(path) = opts.output_file {
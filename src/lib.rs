@@ -4,13 +4,16 @@ extern crate itertools;
 #[macro_use]
 extern crate serde;
 
+mod options;
 mod stage1;
 mod stage2;
 mod types;
 
+pub use options::{Flavor, Options};
+
 // Run the transformation
-pub fn run(conn_config: &postgres::config::Config) -> codegen::Scope {
+pub fn run(conn_config: &postgres::config::Config, options: &Options) -> codegen::Scope {
     let pg_defs = stage1::run(&conn_config);
-    let rs_defs = stage2::run(pg_defs);
+    let rs_defs = stage2::run(pg_defs, options);
     rs_defs
 }
@@ -0,0 +1,36 @@
+// --------------------------------------------------------------------------------------------------------------------
+// Generator options
+// --------------------------------------------------------------------------------------------------------------------
+
+/// Options that control the generated code, but not the introspection
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    pub flavor: Flavor,
+}
+
+/// Kind of client the generated bindings are written for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flavor {
+    /// Blocking functions over the postgres crate
+    Sync,
+    /// Async functions over the tokio-postgres crate
+    Async,
+}
+
+impl Default for Flavor {
+    fn default() -> Self {
+        Flavor::Sync
+    }
+}
+
+impl std::str::FromStr for Flavor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sync" => Ok(Flavor::Sync),
+            "async" => Ok(Flavor::Async),
+            e => Err(format!("Unknown flavor '{}'", e)),
+        }
+    }
+}
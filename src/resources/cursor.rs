@@ -0,0 +1,59 @@
+/// Rows of a cursor returned by a function, fetched in batches
+/// An empty batch ends the rows too, a fetch size that isn't positive never moves the cursor forward
+/// The transaction is committed once every row has been read, and rolled back if the cursor is dropped before
+pub struct Cursor<'a, T> {
+    transaction: Option<postgres::Transaction<'a>>,
+    name: String,
+    fetch_size: i64,
+    buffer: std::vec::IntoIter<postgres::Row>,
+    done: bool,
+    marker: std::marker::PhantomData<T>,
+}
+
+impl<'a, T> Cursor<'a, T> {
+    pub fn new(transaction: postgres::Transaction<'a>, name: String, fetch_size: i64) -> Self {
+        Self {
+            transaction: Some(transaction),
+            name,
+            fetch_size,
+            buffer: Vec::new().into_iter(),
+            done: false,
+            marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Close the cursor before every row has been read, and commit the transaction it was opened in
+    pub fn close(mut self) -> PgResult<()> {
+        if let Some(transaction) = self.transaction.take() {
+            transaction.commit()?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, T: FromPostgresRow> Iterator for Cursor<'a, T> {
+    type Item = PgResult<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(row) = self.buffer.next() {
+                return Some(T::from_postgres_row(row).map_err(PgError::from));
+            }
+            if self.done {
+                return self.transaction.take()?.commit().err().map(|e| Err(e.into()));
+            }
+            let transaction = self.transaction.as_mut()?;
+            let fetch = format!("FETCH {} FROM \"{}\"", self.fetch_size, self.name.replace('"', "\"\""));
+            match transaction.query(fetch.as_str(), &[]) {
+                Ok(rows) => {
+                    self.done = rows.is_empty() || (rows.len() as i64) < self.fetch_size;
+                    self.buffer = rows.into_iter();
+                }
+                Err(e) => {
+                    self.transaction = None;
+                    return Some(Err(e.into()));
+                }
+            }
+        }
+    }
+}
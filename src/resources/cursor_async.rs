@@ -0,0 +1,34 @@
+/// Rows of a cursor returned by a function, fetched in batches
+/// An empty batch ends the rows too, a fetch size that isn't positive never moves the cursor forward
+pub type Cursor<'a, T> = std::pin::Pin<Box<dyn futures_util::Stream<Item = PgResult<T>> + Send + 'a>>;
+
+/// Open a cursor inside a new transaction, which is committed once every row has been read and rolled back if the
+/// stream is dropped before
+pub async fn query_cursor<'a, C, T>(
+    db: &'a mut C,
+    query: &str,
+    params: &[&(dyn tokio_postgres::types::ToSql + Sync)],
+    fetch_size: i64,
+) -> PgResult<Cursor<'a, T>>
+where
+    C: tokio_postgres::GenericClient,
+    T: FromPostgresRow + Send + 'a,
+{
+    use futures_util::{StreamExt, TryStreamExt};
+
+    let transaction = db.transaction().await?;
+    let row = transaction.query(query, params).await?.into_iter().next().ok_or(PgError::KeyNotFound)?;
+    let name: String = row.get(0);
+    let fetch = format!("FETCH {} FROM \"{}\"", fetch_size, name.replace('"', "\"\""));
+    let batches = futures_util::stream::try_unfold((transaction, fetch, false), move |(transaction, fetch, done)| async move {
+        if done {
+            transaction.commit().await?;
+            return Ok(None);
+        }
+        let rows = transaction.query(fetch.as_str(), &[]).await?;
+        let done = rows.is_empty() || (rows.len() as i64) < fetch_size;
+        Ok(Some((futures_util::stream::iter(rows.into_iter().map(Ok)), (transaction, fetch, done))))
+    });
+    let rows = batches.try_flatten().map(|row: Result<tokio_postgres::Row, tokio_postgres::Error>| Ok(T::from_postgres_row(row?)?));
+    Ok(Box::pin(rows))
+}
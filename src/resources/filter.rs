@@ -0,0 +1,219 @@
+/// Column of a table or view, along with the Rust type of its values
+pub trait Column: Copy {
+    type Table: TableMeta;
+    type Value: ToSql + Sync + Send + 'static;
+
+    /// Quoted name
+    fn sql_name() -> &'static str;
+
+    fn eq(self, value: impl Into<Self::Value>) -> Filter<Self::Table> {
+        Filter::compare(Self::sql_name(), "=", value.into())
+    }
+
+    fn ne(self, value: impl Into<Self::Value>) -> Filter<Self::Table> {
+        Filter::compare(Self::sql_name(), "<>", value.into())
+    }
+
+    fn lt(self, value: impl Into<Self::Value>) -> Filter<Self::Table> {
+        Filter::compare(Self::sql_name(), "<", value.into())
+    }
+
+    fn le(self, value: impl Into<Self::Value>) -> Filter<Self::Table> {
+        Filter::compare(Self::sql_name(), "<=", value.into())
+    }
+
+    fn gt(self, value: impl Into<Self::Value>) -> Filter<Self::Table> {
+        Filter::compare(Self::sql_name(), ">", value.into())
+    }
+
+    fn ge(self, value: impl Into<Self::Value>) -> Filter<Self::Table> {
+        Filter::compare(Self::sql_name(), ">=", value.into())
+    }
+
+    fn like(self, pattern: impl Into<String>) -> Filter<Self::Table>
+    where
+        Self: Column<Value = String>,
+    {
+        Filter::compare(Self::sql_name(), "LIKE", pattern.into())
+    }
+
+    /// An empty list matches nothing
+    fn is_in<V: Into<Self::Value>>(self, values: impl IntoIterator<Item = V>) -> Filter<Self::Table> {
+        let values = values.into_iter().map(|v| Box::new(v.into()) as Box<dyn ToSql + Sync + Send>).collect();
+        Filter::new(Condition::In(Self::sql_name(), values))
+    }
+
+    fn is_null(self) -> Filter<Self::Table> {
+        Filter::new(Condition::IsNull(Self::sql_name(), true))
+    }
+
+    fn is_not_null(self) -> Filter<Self::Table> {
+        Filter::new(Condition::IsNull(Self::sql_name(), false))
+    }
+
+    fn asc(self) -> Order<Self::Table> {
+        Order::new(format!("{} ASC", Self::sql_name()))
+    }
+
+    fn desc(self) -> Order<Self::Table> {
+        Order::new(format!("{} DESC", Self::sql_name()))
+    }
+}
+
+/// Condition on the rows of a table or view
+pub struct Filter<T> {
+    condition: Condition,
+    marker: std::marker::PhantomData<fn() -> T>,
+}
+
+enum Condition {
+    Compare(&'static str, &'static str, Box<dyn ToSql + Sync + Send>),
+    In(&'static str, Vec<Box<dyn ToSql + Sync + Send>>),
+    IsNull(&'static str, bool),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+    Not(Box<Condition>),
+}
+
+impl<T> Filter<T> {
+    fn new(condition: Condition) -> Self {
+        Self {
+            condition,
+            marker: std::marker::PhantomData,
+        }
+    }
+
+    fn compare(column: &'static str, operator: &'static str, value: impl ToSql + Sync + Send + 'static) -> Self {
+        Self::new(Condition::Compare(column, operator, Box::new(value)))
+    }
+
+    pub fn and(self, other: Filter<T>) -> Self {
+        Self::new(Condition::And(Box::new(self.condition), Box::new(other.condition)))
+    }
+
+    pub fn or(self, other: Filter<T>) -> Self {
+        Self::new(Condition::Or(Box::new(self.condition), Box::new(other.condition)))
+    }
+}
+
+impl<T> std::ops::Not for Filter<T> {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self::new(Condition::Not(Box::new(self.condition)))
+    }
+}
+
+impl Condition {
+    /// Write the condition, numbering the placeholders after the parameters already there
+    fn to_sql<'a>(&'a self, sql: &mut String, params: &mut Vec<&'a (dyn ToSql + Sync)>) {
+        match self {
+            Condition::Compare(column, operator, value) => {
+                params.push(&**value);
+                sql.push_str(&format!("{} {} ${}", column, operator, params.len()));
+            }
+            Condition::In(_, values) if values.is_empty() => sql.push_str("FALSE"),
+            Condition::In(column, values) => {
+                let mut placeholders = Vec::new();
+                for value in values {
+                    params.push(&**value);
+                    placeholders.push(format!("${}", params.len()));
+                }
+                sql.push_str(&format!("{} IN ({})", column, placeholders.join(", ")));
+            }
+            Condition::IsNull(column, true) => sql.push_str(&format!("{} IS NULL", column)),
+            Condition::IsNull(column, false) => sql.push_str(&format!("{} IS NOT NULL", column)),
+            Condition::And(left, right) | Condition::Or(left, right) => {
+                let operator = if let Condition::And(..) = self { "AND" } else { "OR" };
+                sql.push('(');
+                left.to_sql(sql, params);
+                sql.push_str(&format!(") {} (", operator));
+                right.to_sql(sql, params);
+                sql.push(')');
+            }
+            Condition::Not(inner) => {
+                sql.push_str("NOT (");
+                inner.to_sql(sql, params);
+                sql.push(')');
+            }
+        }
+    }
+}
+
+/// Sort order of the rows of a table or view
+pub struct Order<T> {
+    sql: String,
+    marker: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T> Order<T> {
+    fn new(sql: String) -> Self {
+        Self {
+            sql,
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+/// Query over a table or view, built from typed filters
+pub struct Query<T> {
+    filter: Option<Condition>,
+    order: Vec<String>,
+    limit: Option<i64>,
+    marker: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T: TableMeta> Default for Query<T> {
+    fn default() -> Self {
+        Self {
+            filter: None,
+            order: Vec::new(),
+            limit: None,
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T: TableMeta> Query<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a condition, all of them must hold
+    pub fn filter(mut self, filter: Filter<T>) -> Self {
+        self.filter = Some(match self.filter.take() {
+            Some(previous) => Condition::And(Box::new(previous), Box::new(filter.condition)),
+            None => filter.condition,
+        });
+        self
+    }
+
+    /// Sort by one more column
+    pub fn order_by(mut self, order: Order<T>) -> Self {
+        self.order.push(order.sql);
+        self
+    }
+
+    pub fn limit(mut self, limit: i64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Get the SQL text and its parameters
+    pub fn to_sql(&self) -> (String, Vec<&(dyn ToSql + Sync)>) {
+        let mut sql = T::SELECT.to_string();
+        let mut params: Vec<&(dyn ToSql + Sync)> = Vec::new();
+        if let Some(filter) = &self.filter {
+            sql.push_str(" WHERE ");
+            filter.to_sql(&mut sql, &mut params);
+        }
+        if !self.order.is_empty() {
+            sql.push_str(&format!(" ORDER BY {}", self.order.join(", ")));
+        }
+        if let Some(limit) = &self.limit {
+            params.push(limit);
+            sql.push_str(&format!(" LIMIT ${}", params.len()));
+        }
+        (sql, params)
+    }
+}
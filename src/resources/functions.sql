@@ -25,8 +25,13 @@ select
     	then array(select unnest(p.proargtypes))
     	else array(select unnest(p.proallargtypes))
 	end as "arg_types",
+    case when p.proargmodes is null
+    	then array(select pg_catalog.format_type(unnest(p.proargtypes), null))
+    	else array(select pg_catalog.format_type(unnest(p.proallargtypes), null))
+	end as "arg_type_names",
     p.prorettype as "ret_type",
-    p.proretset as "ret_set"
+    p.proretset as "ret_set",
+    pg_catalog.obj_description ( p.oid, 'pg_proc' ) as "description"
 from pg_proc p
 join pg_namespace ns on (p.pronamespace = ns.oid)
 where probin is null and pronamespace in (2200);
@@ -0,0 +1,13 @@
+/// Names of a table or view and of its columns, as found in the database
+pub trait TableMeta {
+    const SCHEMA: &'static str;
+    const NAME: &'static str;
+    /// Quoted and qualified name, ready to be used in queries
+    const SQL_NAME: &'static str;
+    /// Column names, in table order
+    const COLUMNS: &'static [&'static str];
+    /// Primary key columns in key order, views only have one when it's declared with `@key`
+    const PRIMARY_KEY: &'static [&'static str];
+    /// Query of every column, to be followed by the rest of the clauses
+    const SELECT: &'static str;
+}
@@ -0,0 +1,8 @@
+/// Deserialize a field of a patch that is present, even if it's null, so it can be told apart from a missing one
+fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: serde::Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
@@ -0,0 +1,13 @@
+SELECT
+    c.oid,
+    n.nspname AS "schema",
+    c.relname AS "name",
+    s.seqtypid AS "typ",
+    pg_catalog.format_type(s.seqtypid, NULL) AS "sql_type"
+FROM pg_catalog.pg_sequence s
+JOIN pg_catalog.pg_class c ON c.oid = s.seqrelid
+JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+WHERE
+    n.nspname <> 'information_schema'
+    AND n.nspname NOT LIKE 'pg_%'
+ORDER BY "schema", "name"
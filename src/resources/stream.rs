@@ -0,0 +1,83 @@
+/// Rows of a set-returning function, decoded as they arrive
+pub struct RowStream<'a, T> {
+    source: RowSource<'a>,
+    decode: fn(postgres::Row) -> PgResult<T>,
+}
+
+enum RowSource<'a> {
+    Query(postgres::RowIter<'a>),
+    Portal {
+        transaction: Option<postgres::Transaction<'a>>,
+        portal: postgres::Portal,
+        fetch_size: i32,
+        buffer: std::vec::IntoIter<postgres::Row>,
+        done: bool,
+    },
+}
+
+impl<'a, T> RowStream<'a, T> {
+    /// Run the query, without a fetch size all the rows are sent at once by the server,
+    /// otherwise they are read in batches through a portal inside a new transaction, which is committed once every
+    /// row has been read and rolled back if the stream is dropped before
+    pub fn query<C: postgres::GenericClient>(
+        db: &'a mut C,
+        query: &str,
+        params: &[&(dyn postgres::types::ToSql + Sync)],
+        fetch_size: Option<i32>,
+        decode: fn(postgres::Row) -> PgResult<T>,
+    ) -> PgResult<Self> {
+        let source = match fetch_size {
+            None => RowSource::Query(db.query_raw(query, params.iter().copied())?),
+            Some(fetch_size) => {
+                let mut transaction = db.transaction()?;
+                let portal = transaction.bind(query, params)?;
+                RowSource::Portal {
+                    transaction: Some(transaction),
+                    portal,
+                    fetch_size,
+                    buffer: Vec::new().into_iter(),
+                    done: false,
+                }
+            }
+        };
+        Ok(Self { source, decode })
+    }
+}
+
+impl<'a, T> Iterator for RowStream<'a, T> {
+    type Item = PgResult<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let row = match &mut self.source {
+            RowSource::Query(rows) => postgres::fallible_iterator::FallibleIterator::next(rows).transpose()?,
+            RowSource::Portal {
+                transaction,
+                portal,
+                fetch_size,
+                buffer,
+                done,
+            } => loop {
+                if let Some(row) = buffer.next() {
+                    break Ok(row);
+                }
+                if *done {
+                    match transaction.take()?.commit() {
+                        Ok(()) => return None,
+                        Err(e) => break Err(e),
+                    }
+                }
+                match transaction.as_mut()?.query_portal(portal, *fetch_size) {
+                    Ok(rows) => {
+                        *done = rows.is_empty() || (rows.len() as i32) < *fetch_size;
+                        *buffer = rows.into_iter();
+                    }
+                    Err(e) => {
+                        *transaction = None;
+                        break Err(e);
+                    }
+                }
+            },
+        };
+        Some(row.map_err(PgError::from).and_then(self.decode))
+    }
+}
@@ -0,0 +1,41 @@
+/// Rows of a set-returning function, decoded as they arrive
+pub type RowStream<'a, T> = std::pin::Pin<Box<dyn futures_util::Stream<Item = PgResult<T>> + Send + 'a>>;
+
+/// Run the query, without a fetch size all the rows are sent at once by the server,
+/// otherwise they are read in batches through a portal inside a new transaction, which is committed once every
+/// row has been read and rolled back if the stream is dropped before
+pub async fn query_stream<'a, C, T>(
+    db: &'a mut C,
+    query: &str,
+    params: &[&(dyn tokio_postgres::types::ToSql + Sync)],
+    fetch_size: Option<i32>,
+    decode: fn(tokio_postgres::Row) -> PgResult<T>,
+) -> PgResult<RowStream<'a, T>>
+where
+    C: tokio_postgres::GenericClient,
+    T: Send + 'a,
+{
+    use futures_util::{StreamExt, TryStreamExt};
+
+    match fetch_size {
+        None => {
+            let rows = db.query_raw(query, params.iter().copied()).await?;
+            Ok(Box::pin(rows.map(move |row| decode(row?))))
+        }
+        Some(fetch_size) => {
+            let transaction = db.transaction().await?;
+            let portal = transaction.bind(query, params).await?;
+            let batches = futures_util::stream::try_unfold((transaction, portal, false), move |(transaction, portal, done)| async move {
+                if done {
+                    transaction.commit().await?;
+                    return Ok(None);
+                }
+                let rows = transaction.query_portal(&portal, fetch_size).await?;
+                let done = rows.is_empty() || (rows.len() as i32) < fetch_size;
+                Ok(Some((futures_util::stream::iter(rows.into_iter().map(Ok)), (transaction, portal, done))))
+            });
+            let rows = batches.try_flatten().map(move |row: Result<tokio_postgres::Row, tokio_postgres::Error>| decode(row?));
+            Ok(Box::pin(rows))
+        }
+    }
+}
@@ -8,10 +8,61 @@ select
         WHEN t.typcategory = 'A' THEN 'a'
     	ELSE t.typtype 
 	END AS "kind",
-	-- For composites, is this a type, a table, or a view?
-	exists (select * from pg_catalog.pg_tables pt where pt.schemaname = n.nspname and pt.tablename = t.typname) as is_table,
+	-- For composites, is this a type, a table, or a view? Partitioned and foreign tables count as tables
+	coalesce(c.relkind IN ('r', 'p', 'f'), false) as is_table,
 	exists (select * from pg_catalog.pg_views pv where pv.schemaname = n.nspname and pv.viewname = t.typname) or 
 	exists (select * from pg_catalog.pg_matviews pm where pm.schemaname = n.nspname and pm.matviewname = t.typname) as is_view,
+    coalesce(c.relkind = 'm', false) AS "is_materialized",
+    -- Partitions share the row type of their parent table
+    CASE
+        WHEN c.relispartition THEN (
+            SELECT pc.reltype
+            FROM pg_catalog.pg_inherits inh
+            JOIN pg_catalog.pg_class pc ON pc.oid = inh.inhparent
+            WHERE inh.inhrelid = c.oid
+        )
+        ELSE 0
+    END AS "partition_of",
+    -- Parent tables with classic inheritance, partitions are left out since they don't have a row type of their own
+    array(
+        SELECT pc.reltype
+        FROM pg_catalog.pg_inherits inh
+        JOIN pg_catalog.pg_class pc ON pc.oid = inh.inhparent
+        WHERE inh.inhrelid = t.typrelid AND NOT c.relispartition
+        ORDER BY inh.inhseqno
+    ) AS "parents",
+    -- Partition key of partitioned tables, with the columns up to the first expression
+    (SELECT to_jsonb(pk) FROM (
+        SELECT
+            CASE pt.partstrat WHEN 'h' THEN 'hash' WHEN 'l' THEN 'list' WHEN 'r' THEN 'range' END AS "strategy",
+            array(
+                SELECT attrib.attname::text
+                FROM unnest(pt.partattrs::int2[]) WITH ORDINALITY AS k(attnum, pos)
+                JOIN pg_catalog.pg_attribute attrib ON attrib.attrelid = pt.partrelid AND attrib.attnum = k.attnum
+                WHERE k.pos < ALL (
+                    SELECT e.pos FROM unnest(pt.partattrs::int2[]) WITH ORDINALITY AS e(attnum, pos) WHERE e.attnum = 0
+                )
+                ORDER BY k.pos
+            ) AS "columns",
+            pt.partexprs IS NOT NULL AS "has_expressions"
+        FROM pg_catalog.pg_partitioned_table pt
+        WHERE pt.partrelid = t.typrelid
+    ) pk) AS "partition_key",
+    -- Which statements can modify the rows, views count when they are auto-updatable or have INSTEAD OF triggers
+    coalesce((pg_catalog.pg_relation_is_updatable(c.oid, true) & 8) = 8, false) AS "is_insertable",
+    coalesce((pg_catalog.pg_relation_is_updatable(c.oid, true) & 4) = 4, false) AS "is_updatable",
+    coalesce((pg_catalog.pg_relation_is_updatable(c.oid, true) & 16) = 16, false) AS "is_deletable",
+    -- Comments of tables and views are stored in the relation, not in its row type
+    coalesce(pg_catalog.obj_description(c.oid, 'pg_class'), '') AS "relation_description",
+    -- Primary key columns, in key order
+    array(
+        SELECT attrib.attname::text
+        FROM pg_catalog.pg_constraint con
+        CROSS JOIN unnest(con.conkey) WITH ORDINALITY AS k(attnum, pos)
+        JOIN pg_catalog.pg_attribute attrib ON attrib.attrelid = con.conrelid AND attrib.attnum = k.attnum
+        WHERE con.conrelid = t.typrelid AND con.contype = 'p'
+        ORDER BY k.pos
+    ) AS "primary_key",
     -- Enum values,
     array(SELECT e.enumlabel FROM pg_enum e WHERE t.oid = e.enumtypid) AS "enum_values",
     -- Composite fields
@@ -25,13 +76,96 @@ select
             --t2.typname as "type_name",            
             NOT attrib.attnotnull AS "is_nullable",
             --not attrib.attnotnull AS "is_nullable",
-            pg_catalog.col_description ( attrib.attrelid, attrib.attnum ) AS "description"
+            pg_catalog.col_description ( attrib.attrelid, attrib.attnum ) AS "description",
+            attrib.atthasdef AS "has_default",
+            attrib.attidentity::text AS "identity",
+            attrib.attgenerated <> '' AS "is_generated"
         FROM pg_catalog.pg_attribute attrib
         --join pg_type t2 on attrib.atttypid = t2.oid
         --JOIN pg_catalog.pg_namespace n2 ON n2.oid = t2.typnamespace
         WHERE attrib.attrelid = t.typrelid AND attrib.attnum > 0 AND NOT attrib.attisdropped
         ORDER BY attrib.attnum 
     ) field) AS struct_fields,
+    -- Indexes of tables, along with the constraints they implement
+    (SELECT coalesce(jsonb_agg(idx), '[]'::jsonb) FROM (
+        SELECT
+            ic.relname::text AS "name",
+            con.conname::text AS "constraint",
+            am.amname::text AS "method",
+            -- Key columns, up to the first expression
+            array(
+                SELECT attrib.attname::text
+                FROM unnest(i.indkey[0:i.indnkeyatts - 1]) WITH ORDINALITY AS k(attnum, pos)
+                JOIN pg_catalog.pg_attribute attrib ON attrib.attrelid = i.indrelid AND attrib.attnum = k.attnum
+                WHERE k.pos < ALL (
+                    SELECT e.pos FROM unnest(i.indkey[0:i.indnkeyatts - 1]) WITH ORDINALITY AS e(attnum, pos) WHERE e.attnum = 0
+                )
+                ORDER BY k.pos
+            ) AS "columns",
+            i.indisunique AS "is_unique",
+            i.indisprimary AS "is_primary",
+            i.indpred IS NOT NULL AS "is_partial",
+            i.indexprs IS NOT NULL AS "has_expressions",
+            -- Copies of the index in the partitions, at any depth
+            array(
+                WITH RECURSIVE parts AS (
+                    SELECT inh.inhrelid FROM pg_catalog.pg_inherits inh WHERE inh.inhparent = i.indexrelid
+                    UNION ALL
+                    SELECT inh.inhrelid FROM pg_catalog.pg_inherits inh JOIN parts ON inh.inhparent = parts.inhrelid
+                )
+                SELECT pc.relname::text FROM parts JOIN pg_catalog.pg_class pc ON pc.oid = parts.inhrelid ORDER BY 1
+            ) AS "partition_names"
+        FROM pg_catalog.pg_index i
+        JOIN pg_catalog.pg_class ic ON ic.oid = i.indexrelid
+        JOIN pg_catalog.pg_am am ON am.oid = ic.relam
+        LEFT JOIN pg_catalog.pg_constraint con
+            ON con.conindid = i.indexrelid AND con.conrelid = i.indrelid AND con.contype IN ('p', 'u', 'x')
+        WHERE i.indrelid = t.typrelid
+        ORDER BY ic.relname
+    ) idx) AS "indexes",
+    -- Foreign keys of tables, the referenced table is identified by its row type
+    -- Keys to partitioned tables are cloned for every partition, only the original one is kept
+    (SELECT coalesce(jsonb_agg(fk), '[]'::jsonb) FROM (
+        SELECT
+            con.conname::text AS "name",
+            array(
+                SELECT attrib.attname::text
+                FROM unnest(con.conkey) WITH ORDINALITY AS k(attnum, pos)
+                JOIN pg_catalog.pg_attribute attrib ON attrib.attrelid = con.conrelid AND attrib.attnum = k.attnum
+                ORDER BY k.pos
+            ) AS "columns",
+            rc.reltype::int8 AS "ref_type",
+            array(
+                SELECT attrib.attname::text
+                FROM unnest(con.confkey) WITH ORDINALITY AS k(attnum, pos)
+                JOIN pg_catalog.pg_attribute attrib ON attrib.attrelid = con.confrelid AND attrib.attnum = k.attnum
+                ORDER BY k.pos
+            ) AS "ref_columns"
+        FROM pg_catalog.pg_constraint con
+        JOIN pg_catalog.pg_class rc ON rc.oid = con.confrelid
+        WHERE con.conrelid = t.typrelid AND con.contype = 'f' AND con.conparentid = 0
+        ORDER BY con.conname
+    ) fk) AS "foreign_keys",
+    -- Named constraints of tables, their names are reported by the errors of the statements that violate them
+    -- The constraints cloned for partitions are left out, but the names of the indexes of unique constraints in
+    -- the partitions are kept, since the errors on the rows of a partition report them
+    (SELECT coalesce(jsonb_agg(cons), '[]'::jsonb) FROM (
+        SELECT
+            con.conname::text AS "name",
+            con.contype::text AS "kind",
+            -- Copies of the index in the partitions, at any depth, there are none for constraints without an index
+            array(
+                WITH RECURSIVE parts AS (
+                    SELECT inh.inhrelid FROM pg_catalog.pg_inherits inh WHERE inh.inhparent = con.conindid
+                    UNION ALL
+                    SELECT inh.inhrelid FROM pg_catalog.pg_inherits inh JOIN parts ON inh.inhparent = parts.inhrelid
+                )
+                SELECT pc.relname::text FROM parts JOIN pg_catalog.pg_class pc ON pc.oid = parts.inhrelid ORDER BY 1
+            ) AS "partition_names"
+        FROM pg_catalog.pg_constraint con
+        WHERE con.conrelid = t.typrelid AND con.contype IN ('p', 'u', 'f', 'c', 'x') AND con.conparentid = 0
+        ORDER BY con.conname
+    ) cons) AS "constraints",
     -- Base type for domains, arrays and ranges
     CASE 
     	WHEN t.typtype = 'd' THEN t.typbasetype
@@ -43,7 +177,7 @@ FROM pg_catalog.pg_type t
 JOIN pg_catalog.pg_namespace n ON n.oid = t.typnamespace
 LEFT JOIN pg_catalog.pg_class c ON c.oid = t.typrelid
 WHERE
-	(t.typrelid = 0 OR c.relkind IN ('e', 'c', 'd', 'r', 'a', 'v', 'm') )
+	(t.typrelid = 0 OR c.relkind IN ('e', 'c', 'd', 'r', 'a', 'v', 'm', 'p', 'f') )
     AND n.nspname <> 'information_schema'
     AND n.nspname NOT LIKE 'pg_%'
 ORDER BY "schema", "name"
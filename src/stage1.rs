@@ -1,5 +1,6 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
+use heck::CamelCase;
 use postgres::types::{accepts, FromSql, Type};
 use std::error::Error;
 
@@ -30,10 +31,13 @@ pub fn run(conn_config: &postgres::config::Config) -> PgDatabase {
         }
     }
 
+    // Lookup tables become enums, which are also used by the columns referencing them
+    read_lookups(&mut client, &mut database);
+
     // Read the user functions and procedures
     let functions_sql = include_str!("resources/functions.sql");
     for row in client.query(functions_sql, &[]).unwrap() {
-        match parse_function(row) {
+        match parse_function(&database.types, row) {
             Ok((oid, func)) => {
                 database.functions.insert(oid, func);
             }
@@ -43,6 +47,13 @@ pub fn run(conn_config: &postgres::config::Config) -> PgDatabase {
         }
     }
 
+    // Read the user sequences
+    let sequences_sql = include_str!("resources/sequences.sql");
+    for row in client.query(sequences_sql, &[]).unwrap() {
+        let (oid, sequence) = parse_sequence(row);
+        database.sequences.insert(oid, sequence);
+    }
+
     // Done
     return database;
 }
@@ -91,7 +102,14 @@ impl PgDatabase {
 
         // Add the system catalog
         let functions = BTreeMap::new();
-        Self { types, functions }
+        let sequences = BTreeMap::new();
+        let lookups = BTreeMap::new();
+        Self {
+            types,
+            functions,
+            sequences,
+            lookups,
+        }
     }
 }
 
@@ -107,12 +125,34 @@ fn parse_type(row: postgres::row::Row) -> Result<(Oid, PgType), String> {
     let kind = row.get("kind");
     let is_table = row.get("is_table");
     let is_view = row.get("is_view");
+    let is_materialized = row.get("is_materialized");
+    let is_insertable = row.get("is_insertable");
+    let is_updatable = row.get("is_updatable");
+    let is_deletable = row.get("is_deletable");
+    let relation_description: String = row.get("relation_description");
 
     // Only one of these fields will be used, depending on the kind of type
     let enum_values = row.get("enum_values");
     let struct_fields = row.get("struct_fields");
+    let mut primary_key: Vec<String> = row.get("primary_key");
+    let indexes = row.get("indexes");
+    let foreign_keys = row.get("foreign_keys");
+    let constraints = row.get("constraints");
+    let partition_of: Oid = row.get("partition_of");
+    let partition_key: Option<serde_json::Value> = row.get("partition_key");
+    let parents = row.get("parents");
     let base_type = row.get("base_type");
 
+    // Lookup tables are marked in a comment, along with the column used to name the values
+    let lookup = parse_annotation(&relation_description, "@lookup");
+
+    // Views have no constraints, but their key can be given in a comment
+    if is_view && primary_key.is_empty() {
+        if let Some(key) = parse_annotation_list(&relation_description, "@key") {
+            primary_key = key;
+        }
+    }
+
     match kind {
         PgTypeKind::Enum => Ok((
             oid,
@@ -122,6 +162,14 @@ fn parse_type(row: postgres::row::Row) -> Result<(Oid, PgType), String> {
                 values: enum_values,
             },
         )),
+        PgTypeKind::Composite if partition_of != 0 => Ok((
+            oid,
+            PgType::Partition {
+                schema,
+                name,
+                parent: partition_of,
+            },
+        )),
         PgTypeKind::Composite => Ok((
             oid,
             PgType::Composite {
@@ -129,7 +177,18 @@ fn parse_type(row: postgres::row::Row) -> Result<(Oid, PgType), String> {
                 name,
                 is_table,
                 is_view,
+                is_materialized,
+                is_insertable,
+                is_updatable,
+                is_deletable,
                 fields: serde_json::from_value(struct_fields).unwrap(),
+                primary_key,
+                indexes: serde_json::from_value(indexes).unwrap(),
+                foreign_keys: serde_json::from_value(foreign_keys).unwrap(),
+                constraints: serde_json::from_value(constraints).unwrap(),
+                partition_key: partition_key.map(|x| serde_json::from_value(x).unwrap()),
+                parents,
+                lookup,
             },
         )),
         PgTypeKind::Domain => Ok((oid, PgType::Domain { schema, name, base_type })),
@@ -140,16 +199,153 @@ fn parse_type(row: postgres::row::Row) -> Result<(Oid, PgType), String> {
     }
 }
 
+/// Read the rows of every lookup table, and make their key and the foreign keys to it use the enum
+/// The columns keep their integer type, they only point to the lookup table, and so do the columns inherited from them
+///
+fn read_lookups(client: &mut postgres::Client, database: &mut PgDatabase) {
+    let mut lookups = Vec::new();
+    for (oid, typ) in &database.types {
+        if let PgType::Composite {
+            schema,
+            name,
+            fields,
+            primary_key,
+            lookup: Some(label),
+            ..
+        } = typ
+        {
+            match read_lookup(client, schema, name, fields, primary_key, label) {
+                Ok(lookup) => lookups.push((*oid, primary_key[0].clone(), lookup)),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+    }
+
+    for (row_type, key, lookup) in lookups {
+        database.lookups.insert(row_type, lookup);
+        for (oid, typ) in database.types.iter_mut() {
+            if let PgType::Composite { fields, foreign_keys, .. } = typ {
+                let mut columns = foreign_keys
+                    .iter()
+                    .filter(|fk| fk.ref_type == row_type && fk.ref_columns == [key.clone()])
+                    .map(|fk| fk.columns[0].clone())
+                    .collect::<Vec<_>>();
+                if *oid == row_type {
+                    columns.push(key.clone());
+                }
+                for field in fields.iter_mut().filter(|f| columns.contains(&f.name)) {
+                    field.lookup = Some(row_type);
+                }
+            }
+        }
+    }
+
+    // Foreign keys aren't inherited, but the columns of a child table must keep the type of the parent ones,
+    // repeated until the grandchildren are reached too
+    loop {
+        let mut inherited = Vec::new();
+        for (oid, typ) in &database.types {
+            if let PgType::Composite { fields, parents, .. } = typ {
+                for parent in parents {
+                    if let Some(PgType::Composite { fields: parent_fields, .. }) = database.types.get(parent) {
+                        for parent_field in parent_fields.iter().filter(|f| f.lookup.is_some()) {
+                            if fields.iter().any(|f| f.name == parent_field.name && f.lookup.is_none()) {
+                                inherited.push((*oid, parent_field.name.clone(), parent_field.lookup));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        if inherited.is_empty() {
+            break;
+        }
+        for (oid, name, lookup) in inherited {
+            if let Some(PgType::Composite { fields, .. }) = database.types.get_mut(&oid) {
+                for field in fields.iter_mut().filter(|f| f.name == name) {
+                    field.lookup = lookup;
+                }
+            }
+        }
+    }
+}
+
+/// Read the rows of a lookup table, which must have a single integer column as its primary key
+/// Every label must become a distinct Rust name, otherwise the table is left alone
+///
+fn read_lookup(
+    client: &mut postgres::Client,
+    schema: &str,
+    name: &str,
+    fields: &[PgField],
+    primary_key: &[String],
+    label: &str,
+) -> Result<PgLookup, String> {
+    let integers = [Type::INT2.oid(), Type::INT4.oid(), Type::INT8.oid()];
+    let key_type = match primary_key {
+        [key] => fields.iter().find(|f| &f.name == key).map(|f| f.typ),
+        _ => None,
+    };
+    let key_type = match key_type {
+        Some(x) if integers.contains(&x) => x,
+        _ => return Err(format!("Lookup tables need an integer primary key: {}.{}", schema, name)),
+    };
+
+    let sql_name = format!("{}.{}", quote_ident(schema), quote_ident(name));
+    let sql =
+        format!("SELECT {}::int8 AS \"key\", {}::text AS \"label\" FROM {} ORDER BY 1", quote_ident(&primary_key[0]), quote_ident(label), sql_name);
+    let mut values = Vec::new();
+    let mut variants = BTreeSet::new();
+    for row in client.query(sql.as_str(), &[]).map_err(|e| e.to_string())? {
+        let key: i64 = row.get("key");
+        let label: Option<String> = row.get("label");
+        let label = match label {
+            Some(x) => x,
+            None => return Err(format!("Lookup table {}.{} has no label for key {}", schema, name, key)),
+        };
+        let variant = label.to_camel_case();
+        if !variant.starts_with(char::is_alphabetic) || variant == "Self" {
+            return Err(format!("Lookup table {}.{} has a label that can't be a Rust name: {:?}", schema, name, label));
+        }
+        if !variants.insert(variant) {
+            return Err(format!("Lookup table {}.{} has labels that become the same Rust name: {:?}", schema, name, label));
+        }
+        values.push(PgLookupValue { key, label });
+    }
+
+    Ok(PgLookup {
+        schema: schema.to_string(),
+        name: name.to_string(),
+        key_type,
+        values,
+    })
+}
+
+/// Parse a sequence declaration
+/// https://www.postgresql.org/docs/current/catalog-pg-sequence.html
+///
+fn parse_sequence(row: postgres::row::Row) -> (Oid, PgSequence) {
+    let oid = row.get("oid");
+    let sequence = PgSequence {
+        schema: row.get("schema"),
+        name: row.get("name"),
+        typ: row.get("typ"),
+        sql_type: row.get("sql_type"),
+    };
+    (oid, sequence)
+}
+
 /// Parse a function declaration
 /// https://www.postgresql.org/docs/current/catalog-pg-proc.html
 ///
-fn parse_function(row: postgres::row::Row) -> Result<(Oid, PgFunction), String> {
+fn parse_function(types: &BTreeMap<Oid, PgType>, row: postgres::row::Row) -> Result<(Oid, PgFunction), String> {
     // Base information
     let oid = row.get("oid");
     let schema = row.get("schema");
     let name = row.get("name");
     let kind = row.get("kind");
     let is_strict: bool = row.get("is_strict");
+    let description: Option<String> = row.get("description");
 
     // Ignore aggregate and window functions
     if kind == PgProcedureKind::Aggregate {
@@ -179,6 +375,10 @@ fn parse_function(row: postgres::row::Row) -> Result<(Oid, PgFunction), String>
         None => Vec::new(),
         Some(x) => x,
     };
+    let arg_type_names: Vec<String> = match row.get("arg_type_names") {
+        None => Vec::new(),
+        Some(x) => x,
+    };
     let arg_modes: Vec<PgArgumentMode> = match row.get("arg_modes") {
         None => vec![PgArgumentMode::In; arg_names.len()],
         Some(x) => x,
@@ -199,12 +399,13 @@ fn parse_function(row: postgres::row::Row) -> Result<(Oid, PgFunction), String>
     let mut in_args = Vec::new();
     let mut out_args = Vec::new();
     let mut variadic_args = Vec::new();
-    for (n, t, m) in izip!(arg_names, arg_types, arg_modes) {
+    for (n, t, tn, m) in izip!(arg_names, arg_types, arg_type_names, arg_modes) {
         let mode = m;
         let is_variadic = mode == PgArgumentMode::Variadic;
         let foo = PgArgument {
             name: n,
             typ: t,
+            sql_type: tn,
             is_variadic: is_variadic,
             is_nullable: !is_strict,
         };
@@ -232,6 +433,19 @@ fn parse_function(row: postgres::row::Row) -> Result<(Oid, PgFunction), String>
     // Otherwise the return type will be 'record' and it's unnecesary to handle it
     let returns = if kind == PgProcedureKind::Proc {
         PgReturn::Void
+    } else if ret_type == postgres::types::Type::REFCURSOR.oid() && out_args.len() == 0 {
+        // The rows of a cursor can't be known in advance, the function must declare them in its comment
+        if returns_set {
+            return Err(format!("Functions returning a set of cursors are not supported: {} -> {}.{}", oid, schema, name));
+        }
+        let row_type = match description.as_ref().and_then(|d| parse_annotation(d, "@cursor")) {
+            Some(x) => x,
+            None => return Err(format!("Cursor functions need a '@cursor <type>' annotation: {} -> {}.{}", oid, schema, name)),
+        };
+        match find_type(types, &row_type) {
+            Some(x) => PgReturn::Cursor(x),
+            None => return Err(format!("Unknown cursor row type '{}': {} -> {}.{}", row_type, oid, schema, name)),
+        }
     } else if out_args.len() == 0 {
         if ret_type == postgres::types::Type::VOID.oid() {
             PgReturn::Void
@@ -257,6 +471,54 @@ fn parse_function(row: postgres::row::Row) -> Result<(Oid, PgFunction), String>
     ))
 }
 
+/// Extract the value of an annotation, such as '@cursor public.my_type', from a comment
+///
+fn parse_annotation(description: &str, annotation: &str) -> Option<String> {
+    description
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix(annotation))
+        .filter_map(|value| value.split_whitespace().next())
+        .map(|value| value.to_string())
+        .next()
+}
+
+/// Extract the values of an annotation that takes a list, such as '@key a, b', from a comment
+///
+fn parse_annotation_list(description: &str, annotation: &str) -> Option<Vec<String>> {
+    description
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix(annotation))
+        .map(|value| {
+            value
+                .split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+        .next()
+}
+
+/// Find a user type by its name, which may or may not be qualified with the schema
+///
+fn find_type(types: &BTreeMap<Oid, PgType>, qualified_name: &str) -> Option<Oid> {
+    let (type_schema, type_name) = match qualified_name.split_once('.') {
+        Some((s, n)) => (s, n),
+        None => ("public", qualified_name),
+    };
+    types
+        .iter()
+        .find(|(_, typ)| match typ {
+            PgType::Enum { schema, name, .. }
+            | PgType::Composite { schema, name, .. }
+            | PgType::Domain { schema, name, .. }
+            | PgType::Array { schema, name, .. }
+            | PgType::Range { schema, name, .. }
+            | PgType::Partition { schema, name, .. } => schema == type_schema && name == type_name,
+            _ => false,
+        })
+        .map(|(oid, _)| *oid)
+}
+
 /// Parse the typtype field as comming from PostgreSQL
 impl<'a> FromSql<'a> for PgTypeKind {
     fn from_sql(_ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
@@ -1,6 +1,13 @@
+use crate::options::*;
 use crate::types::*;
 use heck::{CamelCase, SnakeCase};
 
+mod constraints;
+mod filters;
+mod lookups;
+mod sequences;
+mod tables;
+
 // TODO: generate type aliases for domains
 
 // --------------------------------------------------------------------------------------------------------------------
@@ -8,7 +15,7 @@ use heck::{CamelCase, SnakeCase};
 // --------------------------------------------------------------------------------------------------------------------
 
 /// Generate the module definitions
-pub fn run(database: PgDatabase) -> codegen::Scope {
+pub fn run(database: PgDatabase, options: &Options) -> codegen::Scope {
     let mut scope = codegen::Scope::new();
 
     scope.import("enum_map", "*");
@@ -25,9 +32,45 @@ pub fn run(database: PgDatabase) -> codegen::Scope {
         }
     }
 
+    // Generate the enums of the lookup tables
+    for (_oid, lookup) in &database.lookups {
+        if let Err(e) = lookups::gen_lookup(&mut scope, &database.types, options, lookup) {
+            eprintln!("{}", e);
+            eprintln!("{:#?}", lookup);
+        }
+    }
+
+    // Generate the table functions
+    for (oid, type_def) in &database.types {
+        if let Err(e) = tables::gen_table(&mut scope, &database.types, options, *oid, type_def) {
+            eprintln!("{}", e);
+            eprintln!("{:#?}", type_def);
+        }
+    }
+
+    // Generate the errors of the table constraints
+    for (oid, type_def) in &database.types {
+        if let Err(e) = constraints::gen_constraint_error(&mut scope, &database.types, options, *oid, type_def) {
+            eprintln!("{}", e);
+            eprintln!("{:#?}", type_def);
+        }
+    }
+
+    // Generate the table metadata and the column markers for the typed queries
+    for (_oid, type_def) in &database.types {
+        if let Err(e) = filters::gen_filters(&mut scope, &database.types, type_def) {
+            eprintln!("{}", e);
+            eprintln!("{:#?}", type_def);
+        }
+    }
+
     // Generate the functions
     for (_oid, func_def) in &database.functions {
-        match gen_function(&database.types, func_def) {
+        let result = match &func_def.returns {
+            PgReturn::Cursor(row_type) => gen_cursor_function(&database.types, options, func_def, *row_type),
+            _ => gen_function(&database.types, options, func_def, false),
+        };
+        match result {
             Ok(new_func) => {
                 scope.push_fn(new_func);
             }
@@ -36,6 +79,88 @@ pub fn run(database: PgDatabase) -> codegen::Scope {
                 eprintln!("{:#?}", func_def);
             }
         }
+
+        // Functions with arguments also get a batch variant
+        if !func_def.arguments.is_empty() && !matches!(func_def.returns, PgReturn::Cursor(_)) {
+            match gen_batch_function(&database.types, options, func_def) {
+                Ok(new_func) => {
+                    scope.push_fn(new_func);
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    eprintln!("{:#?}", func_def);
+                }
+            }
+        }
+
+        // Set-returning functions also get a streaming variant
+        if func_def.returns_set && func_def.kind == PgProcedureKind::Function {
+            match gen_stream_function(&database.types, options, func_def) {
+                Ok(new_func) => {
+                    scope.push_fn(new_func);
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    eprintln!("{:#?}", func_def);
+                }
+            }
+        }
+    }
+
+    // Generate the sequence functions
+    for (_oid, seq_def) in &database.sequences {
+        match sequences::gen_sequence(&database.types, options, seq_def) {
+            Ok(functions) => {
+                for new_func in functions {
+                    scope.push_fn(new_func);
+                }
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                eprintln!("{:#?}", seq_def);
+            }
+        }
+    }
+
+    // Generate the prepared statements
+    if let Err(e) = gen_statements(&mut scope, &database, options) {
+        eprintln!("{}", e);
+    }
+
+    // Support code for the patch structs, only needed when some of them have nullable columns
+    let needs_deserialize_some = database
+        .types
+        .iter()
+        .any(|(oid, t)| tables::needs_deserialize_some(&database.types, *oid, t).unwrap_or(false));
+    if needs_deserialize_some {
+        scope.raw(include_str!("resources/patch.rs"));
+    }
+
+    // Support code for the table metadata and the typed queries
+    let has_relations = database
+        .types
+        .values()
+        .any(|t| matches!(t, PgType::Composite { is_table, is_view, .. } if *is_table || *is_view));
+    if has_relations {
+        scope.raw(include_str!("resources/meta.rs"));
+        scope.raw(include_str!("resources/filter.rs"));
+        filters::gen_query_load(&mut scope, options);
+    }
+
+    // Support code for the functions returning cursors
+    if database.functions.values().any(|f| matches!(f.returns, PgReturn::Cursor(_))) {
+        match options.flavor {
+            Flavor::Sync => scope.raw(include_str!("resources/cursor.rs")),
+            Flavor::Async => scope.raw(include_str!("resources/cursor_async.rs")),
+        };
+    }
+
+    // Support code for the streaming variants of set-returning functions
+    if database.functions.values().any(|f| f.returns_set && f.kind == PgProcedureKind::Function) {
+        match options.flavor {
+            Flavor::Sync => scope.raw(include_str!("resources/stream.rs")),
+            Flavor::Async => scope.raw(include_str!("resources/stream_async.rs")),
+        };
     }
 
     // Done
@@ -55,6 +180,7 @@ fn gen_type(scope: &mut codegen::Scope, database: &BTreeMap<Oid, PgType>, type_d
             is_table,
             is_view,
             fields,
+            ..
         } => gen_composite(scope, &database, &schema, &name, *is_table, *is_view, &fields),
         _ => Ok(()),
     }
@@ -133,7 +259,7 @@ fn gen_composite(
     let mut serializable = true;
     for field in fields {
         let rs_name = gen_fld_name(&field.name);
-        let foo = resolve_fld_type(database, field.typ)?;
+        let foo = resolve_column_type(database, field)?;
         copyable = copyable & foo.copyable;
         serializable = serializable & foo.serializable;
         let rs_type = if field.is_nullable && (is_table || is_view) {
@@ -162,72 +288,45 @@ fn gen_composite(
     Ok(())
 }
 
-fn gen_function(database: &BTreeMap<Oid, PgType>, func_def: &PgFunction) -> Result<codegen::Function, String> {
+/// Generate a function calling a stored procedure
+/// Prepared functions are methods of the `Statements` struct, and reuse its statements instead of sending the query
+///
+fn gen_function(database: &BTreeMap<Oid, PgType>, options: &Options, func_def: &PgFunction, prepared: bool) -> Result<codegen::Function, String> {
     // Create the new function definition
     let mut new_func = codegen::Function::new(&gen_function_name(&func_def.schema, &func_def.name));
 
     // Make it public
     new_func.vis("pub");
+    new_func.set_async(options.flavor == Flavor::Async);
 
     // Function arguments
-    new_func.arg("db", "&mut postgres::Client");
+    if prepared {
+        new_func.arg_ref_self();
+    }
+    new_func.arg("db", gen_client_type(options));
     for arg in &func_def.arguments {
         let arg_type = resolve_arg_type(&database, arg.typ)?;
         new_func.arg(&gen_arg_name(&arg.name), arg_type);
     }
 
     // Function return type
-    let foo = match &func_def.returns {
-        PgReturn::Void => format!("()"),
-        PgReturn::Scalar(typ) => resolve_ret_type(&database, *typ)?,
-        PgReturn::Record(r) => {
-            let foo = r
-                .iter()
-                .map(|field| resolve_ret_type(&database, field.typ))
-                .collect::<Result<Vec<_>, _>>()?
-                .join(", ");
-            format!("({})", foo)
-        }
-    };
+    let foo = resolve_row_type(&database, &func_def.returns)?;
     let func_ret = if func_def.returns_set { format!("Vec<({})>", foo) } else { foo };
     new_func.ret(format!("PgResult<{}>", func_ret));
 
     // Function body
     // 1st part, SQL query
-    let pg_args = (0..func_def.arguments.len())
-        .map(|x| format!("${}", x + 1))
-        .collect::<Vec<String>>()
-        .join(", ");
-    let rs_args = func_def
-        .arguments
-        .iter()
-        .map(|arg| format!("&{}", &gen_arg_name(&arg.name)))
-        .collect::<Vec<String>>()
-        .join(", ");
-    match (func_def.kind, &func_def.returns) {
-        (PgProcedureKind::Function, PgReturn::Void) => {
-            new_func.line(format!(
-                "let _query = db.query(\"SELECT * FROM \\\"{}\\\".\\\"{}\\\"({})\", &[{}])?;",
-                &func_def.schema, &func_def.name, &pg_args, &rs_args
-            ));
-        }
-        (PgProcedureKind::Function, _) => {
-            new_func.line(format!(
-                "let query = db.query(\"SELECT * FROM \\\"{}\\\".\\\"{}\\\"({})\", &[{}])?;",
-                &func_def.schema, &func_def.name, &pg_args, &rs_args
-            ));
-        }
-        (PgProcedureKind::Proc, _) => {
-            new_func.line(format!(
-                "let _query = db.query(\"CALL \\\"{}\\\".\\\"{}\\\"({})\", &[{}])?;",
-                &func_def.schema, &func_def.name, &pg_args, &rs_args
-            ));
-        }
-        _ => panic!(
-            "Unsupported kind of function {:#?}: \\\"{}\\\".\\\"{}\\\"",
-            func_def.kind, func_def.schema, func_def.name
-        ),
-    }
+    let (_, rs_args) = gen_call_args(func_def);
+    let statement = if prepared {
+        format!("&self.{}", gen_function_name(&func_def.schema, &func_def.name))
+    } else {
+        format!("{:?}", gen_call_sql(func_def)?)
+    };
+    let query = match func_def.returns {
+        PgReturn::Void => "_query",
+        _ => "query",
+    };
+    new_func.line(format!("let {} = db.query({}, &[{}]){}?;", query, statement, rs_args, gen_await(options)));
 
     // Function body
     // 2nd part, result extraction
@@ -239,7 +338,7 @@ fn gen_function(database: &BTreeMap<Oid, PgType>, func_def: &PgFunction) -> Resu
         // Returns a single scalar (may actually be a composite)
         (PgReturn::Scalar(typ), false) => {
             new_func.line("let row = query.into_iter().next().ok_or(PgError::KeyNotFound)?;");
-            match database.get(&typ).ok_or("Unknown return type")? {
+            match find_type(database, *typ)? {
                 PgType::Composite { schema, name, .. } => {
                     new_func.line(format!("let result = {}::from_postgres_row(row)?;", gen_type_name(schema, name)));
                 }
@@ -250,7 +349,7 @@ fn gen_function(database: &BTreeMap<Oid, PgType>, func_def: &PgFunction) -> Resu
             new_func.line("Ok(result)");
         }
         // Returns a set of scalars (may actually be a composite)
-        (PgReturn::Scalar(typ), true) => match database.get(&typ).ok_or("Unknown return type")? {
+        (PgReturn::Scalar(typ), true) => match find_type(database, *typ)? {
             PgType::Composite { schema, name, .. } => {
                 new_func.line(format!(
                     "let result: Result<Vec<{0}>, _> = query.iter().map(|row| {0}::from_postgres_row(row)).collect();",
@@ -284,12 +383,389 @@ fn gen_function(database: &BTreeMap<Oid, PgType>, func_def: &PgFunction) -> Resu
             new_func.line(format!("let result = query.into_iter().map(|row| ({})).collect();", foo));
             new_func.line("Ok(result)");
         }
+        // Handled by gen_cursor_function
+        (PgReturn::Cursor(_), _) => unreachable!(),
+    }
+
+    // Done
+    Ok(new_func)
+}
+
+/// Generate a function returning a refcursor
+/// The function is called inside a transaction, and the rows are fetched in batches as the result is iterated
+///
+fn gen_cursor_function(
+    database: &BTreeMap<Oid, PgType>,
+    options: &Options,
+    func_def: &PgFunction,
+    row_type: Oid,
+) -> Result<codegen::Function, String> {
+    // Create the new function definition
+    let mut new_func = codegen::Function::new(&gen_function_name(&func_def.schema, &func_def.name));
+
+    // Make it public
+    new_func.vis("pub");
+    new_func.set_async(options.flavor == Flavor::Async);
+
+    // Function arguments
+    // The cursor borrows the connection until it's dropped
+    new_func.generic("'a");
+    new_func.arg("db", format!("&'a mut {}", gen_client_trait(options)));
+    for arg in &func_def.arguments {
+        let arg_type = resolve_arg_type(&database, arg.typ)?;
+        new_func.arg(&gen_arg_name(&arg.name), arg_type);
+    }
+    new_func.arg("fetch_size", "i64");
+
+    // Function return type
+    // Only composite types can be used as the row type
+    let row_name = match find_type(database, row_type)? {
+        PgType::Composite { schema, name, .. } => gen_type_name(schema, name),
+        _ => return Err(format!("The rows of a cursor must be a composite type")),
+    };
+    new_func.ret(format!("PgResult<Cursor<'a, {}>>", row_name));
+
+    // Function body
+    // Open the cursor, and keep its name to fetch the rows later
+    let (pg_args, rs_args) = gen_call_args(func_def);
+    let sql = format!("SELECT \"{}\".\"{}\"({})::text", &func_def.schema, &func_def.name, &pg_args);
+    match options.flavor {
+        Flavor::Sync => {
+            new_func.line("let mut transaction = db.transaction()?;");
+            new_func.line(format!("let query = transaction.query({:?}, &[{}])?;", sql, rs_args));
+            new_func.line("let row = query.into_iter().next().ok_or(PgError::KeyNotFound)?;");
+            new_func.line("Ok(Cursor::new(transaction, row.get(0), fetch_size))");
+        }
+        Flavor::Async => {
+            new_func.line(format!("query_cursor(db, {:?}, &[{}], fetch_size).await", sql, rs_args));
+        }
+    }
+
+    // Done
+    Ok(new_func)
+}
+
+/// Generate the streaming variant of a set-returning function
+/// Rows are decoded as they arrive instead of being collected into a vector,
+/// if a fetch size is given they are read in batches through a portal
+///
+fn gen_stream_function(database: &BTreeMap<Oid, PgType>, options: &Options, func_def: &PgFunction) -> Result<codegen::Function, String> {
+    // Create the new function definition
+    let mut new_func = codegen::Function::new(&format!("{}_iter", gen_function_name(&func_def.schema, &func_def.name)));
+
+    // Make it public
+    new_func.vis("pub");
+    new_func.set_async(options.flavor == Flavor::Async);
+
+    // Function arguments
+    // The stream borrows the connection until it's dropped
+    new_func.generic("'a");
+    new_func.arg("db", format!("&'a mut {}", gen_client_trait(options)));
+    for arg in &func_def.arguments {
+        let arg_type = resolve_arg_type(&database, arg.typ)?;
+        new_func.arg(&gen_arg_name(&arg.name), arg_type);
+    }
+    new_func.arg("fetch_size", "Option<i32>");
+
+    // Function return type
+    let row_type = resolve_row_type(&database, &func_def.returns)?;
+    new_func.ret(format!("PgResult<RowStream<'a, {}>>", row_type));
+
+    // Function body
+    let (_, rs_args) = gen_call_args(func_def);
+    let sql = gen_call_sql(func_def)?;
+    let decoder = gen_row_decoder(&database, &func_def.returns)?;
+    match options.flavor {
+        Flavor::Sync => {
+            new_func.line(format!("RowStream::query(db, {:?}, &[{}], fetch_size, |row| Ok({}))", sql, rs_args, decoder));
+        }
+        Flavor::Async => {
+            new_func.line(format!("query_stream(db, {:?}, &[{}], fetch_size, |row| Ok({})).await", sql, rs_args, decoder));
+        }
     }
 
     // Done
     Ok(new_func)
 }
 
+/// Generate the batch variant of a function, which calls it once for every set of arguments
+/// The async flavor pipelines the calls over the connection, the sync flavor runs them inside a single transaction
+/// Either way, the results are returned in the same order as the arguments
+///
+fn gen_batch_function(database: &BTreeMap<Oid, PgType>, options: &Options, func_def: &PgFunction) -> Result<codegen::Function, String> {
+    // Create the new function definition
+    let func_name = gen_function_name(&func_def.schema, &func_def.name);
+    let mut new_func = codegen::Function::new(&format!("{}_batch", func_name));
+
+    // Make it public
+    new_func.vis("pub");
+    new_func.set_async(options.flavor == Flavor::Async);
+
+    // Function arguments
+    // Every element holds the arguments of a call, as a tuple if there are several of them
+    let arg_types = func_def
+        .arguments
+        .iter()
+        .map(|arg| resolve_arg_type(&database, arg.typ))
+        .collect::<Result<Vec<_>, _>>()?;
+    let arg_names = func_def.arguments.iter().map(|arg| gen_arg_name(&arg.name)).collect::<Vec<_>>();
+    let (arg_type, arg_pattern) = if arg_types.len() == 1 {
+        (arg_types[0].clone(), arg_names[0].clone())
+    } else {
+        (format!("({})", arg_types.join(", ")), format!("({})", arg_names.join(", ")))
+    };
+    new_func.arg("db", gen_client_type(options));
+    new_func.arg("args", format!("&[{}]", arg_type));
+
+    // Function return type
+    let foo = resolve_row_type(&database, &func_def.returns)?;
+    let func_ret = if func_def.returns_set { format!("Vec<({})>", foo) } else { foo };
+    new_func.ret(format!("PgResult<Vec<{}>>", func_ret));
+
+    // Function body
+    // All the arguments are either copyable or references, so they can be copied out of the tuple
+    let call_args = arg_names.iter().map(|name| format!(", *{}", name)).collect::<String>();
+    match options.flavor {
+        Flavor::Sync => {
+            new_func.line("let mut transaction = db.transaction()?;");
+            new_func.line("let mut results = Vec::with_capacity(args.len());");
+            let mut block = codegen::Block::new(&format!("for {} in args", arg_pattern));
+            block.line(format!("results.push({}(&mut transaction{})?);", func_name, call_args));
+            new_func.push_block(block);
+            new_func.line("transaction.commit()?;");
+            new_func.line("Ok(results)");
+        }
+        Flavor::Async => {
+            new_func.line(format!("let calls = args.iter().map(|{}| {}(db{}));", arg_pattern, func_name, call_args));
+            new_func.line("futures_util::future::try_join_all(calls).await");
+        }
+    }
+
+    // Done
+    Ok(new_func)
+}
+
+/// Generate the `Statements` struct, which prepares every function once per connection
+/// The type of every argument is sent along with the query if it's a built-in type, the server infers the others
+/// from the casts in the query
+///
+fn gen_statements(scope: &mut codegen::Scope, database: &PgDatabase, options: &Options) -> Result<(), String> {
+    let mut functions = Vec::new();
+    for func_def in database.functions.values().filter(|f| !matches!(f.returns, PgReturn::Cursor(_))) {
+        // The method would clash with the constructor
+        if gen_function_name(&func_def.schema, &func_def.name) == "prepare" {
+            eprintln!("Function {}.{} can't be a method of Statements, skipping it", func_def.schema, func_def.name);
+            continue;
+        }
+        functions.push(func_def);
+    }
+    if functions.is_empty() {
+        return Ok(());
+    }
+    let (statement_type, type_path) = match options.flavor {
+        Flavor::Sync => ("postgres::Statement", "postgres::types::Type"),
+        Flavor::Async => ("tokio_postgres::Statement", "tokio_postgres::types::Type"),
+    };
+
+    // The struct holds one statement per function
+    let new_struct = scope.new_struct("Statements");
+    new_struct.vis("pub");
+    new_struct.derive("Clone");
+    for func_def in &functions {
+        new_struct.field(&format!("pub {}", gen_function_name(&func_def.schema, &func_def.name)), statement_type);
+    }
+
+    // Constructor, prepares every statement
+    let mut prepare = codegen::Function::new("prepare");
+    prepare.vis("pub");
+    prepare.set_async(options.flavor == Flavor::Async);
+    prepare.arg("db", gen_client_type(options));
+    prepare.ret("PgResult<Self>");
+    let mut block = codegen::Block::new("Ok(Self");
+    for func_def in &functions {
+        let types = func_def
+            .arguments
+            .iter()
+            .map(|arg| match postgres::types::Type::from_oid(arg.typ) {
+                Some(typ) => format!("{}::{}", type_path, gen_type_const(&typ)),
+                None => format!("{}::UNKNOWN", type_path),
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+        block.line(format!(
+            "{}: db.prepare_typed({:?}, &[{}]){}?,",
+            gen_function_name(&func_def.schema, &func_def.name),
+            gen_call_sql(func_def)?,
+            types,
+            gen_await(options)
+        ));
+    }
+    block.after(")");
+    prepare.push_block(block);
+
+    // Every function is also a method that reuses its statement
+    let new_impl = scope.new_impl("Statements");
+    new_impl.push_fn(prepare);
+    for func_def in &functions {
+        match gen_function(&database.types, options, func_def, true) {
+            Ok(new_func) => {
+                new_impl.push_fn(new_func);
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                eprintln!("{:#?}", func_def);
+            }
+        }
+    }
+
+    // Done
+    Ok(())
+}
+
+/// Name of the constant of a built-in type, like `INT4` or `INT4_RANGE_ARRAY`
+///
+fn gen_type_const(typ: &postgres::types::Type) -> String {
+    let (name, suffix) = match typ.name().strip_prefix('_') {
+        Some(element) => (element, "_ARRAY"),
+        None => (typ.name(), ""),
+    };
+    let mut name = name.to_uppercase();
+    for word in ["VECTOR", "RANGE"] {
+        if name.ends_with(word) && name.len() > word.len() {
+            name.insert(name.len() - word.len(), '_');
+        }
+    }
+    format!("{}{}", name, suffix)
+}
+
+/// Build the `Type` of a PostgreSQL type, built-in types have a constant and the others are described from their definition
+///
+fn gen_type_value(database: &BTreeMap<Oid, PgType>, options: &Options, oid: Oid) -> Result<String, String> {
+    let types = gen_types_path(options);
+    if let Some(typ) = postgres::types::Type::from_oid(oid) {
+        return Ok(format!("{}::Type::{}", types, gen_type_const(&typ)));
+    }
+    let (schema, name, kind) = match database.get(&oid).ok_or(format!("Unknown type #{}", oid))? {
+        PgType::Enum { schema, name, values } => {
+            let values = values.iter().map(|v| format!("{:?}.to_string()", v)).collect::<Vec<_>>();
+            (schema, name, format!("Enum(vec![{}])", values.join(", ")))
+        }
+        PgType::Composite { schema, name, fields, .. } => {
+            let fields = fields
+                .iter()
+                .map(|f| Ok(format!("{}::Field::new({:?}.to_string(), {})", types, f.name, gen_type_value(database, options, f.typ)?)))
+                .collect::<Result<Vec<_>, String>>()?;
+            (schema, name, format!("Composite(vec![{}])", fields.join(", ")))
+        }
+        PgType::Partition { parent, .. } => return gen_type_value(database, options, *parent),
+        // Domains are sent like their base type, and the fields have the base type too, which only accepts the built-in types
+        PgType::Domain { base_type, .. } => return gen_type_value(database, options, *base_type),
+        PgType::Array { schema, name, base_type } => (schema, name, format!("Array({})", gen_type_value(database, options, *base_type)?)),
+        PgType::Range { schema, name, base_type } => (schema, name, format!("Range({})", gen_type_value(database, options, *base_type)?)),
+        PgType::Base(_) | PgType::Unknown(_) => return Err(format!("Type #{} has no description", oid)),
+    };
+    Ok(format!("{0}::Type::new({1:?}.to_string(), {2}, {0}::Kind::{3}, {4:?}.to_string())", types, name, oid, kind, schema))
+}
+
+/// Type of the connection argument of the generated functions
+///
+fn gen_client_type(options: &Options) -> &'static str {
+    match options.flavor {
+        Flavor::Sync => "&mut impl postgres::GenericClient",
+        Flavor::Async => "&impl tokio_postgres::GenericClient",
+    }
+}
+
+/// Type of the connection argument, for the functions that need to open a transaction
+///
+fn gen_client_trait(options: &Options) -> &'static str {
+    match options.flavor {
+        Flavor::Sync => "impl postgres::GenericClient",
+        Flavor::Async => "impl tokio_postgres::GenericClient",
+    }
+}
+
+/// Type of the query parameters built at runtime
+///
+fn gen_param_type(options: &Options) -> &'static str {
+    match options.flavor {
+        Flavor::Sync => "&(dyn postgres::types::ToSql + Sync)",
+        Flavor::Async => "&(dyn tokio_postgres::types::ToSql + Sync)",
+    }
+}
+
+/// Module with the types to convert values, for the hand-written conversions
+///
+fn gen_types_path(options: &Options) -> &'static str {
+    match options.flavor {
+        Flavor::Sync => "postgres::types",
+        Flavor::Async => "tokio_postgres::types",
+    }
+}
+
+/// Suffix needed to get the result of a query
+///
+fn gen_await(options: &Options) -> &'static str {
+    match options.flavor {
+        Flavor::Sync => "",
+        Flavor::Async => ".await",
+    }
+}
+
+/// Generate the SQL query that calls a stored procedure
+///
+fn gen_call_sql(func_def: &PgFunction) -> Result<String, String> {
+    let (pg_args, _) = gen_call_args(func_def);
+    match func_def.kind {
+        PgProcedureKind::Function => Ok(format!("SELECT * FROM \"{}\".\"{}\"({})", func_def.schema, func_def.name, pg_args)),
+        PgProcedureKind::Proc => Ok(format!("CALL \"{}\".\"{}\"({})", func_def.schema, func_def.name, pg_args)),
+        kind => Err(format!("Unsupported kind of function {:#?}: {}.{}", kind, func_def.schema, func_def.name)),
+    }
+}
+
+/// Generate the placeholders and the Rust values of the arguments of a function call
+/// Every placeholder is cast to the type of the argument, so PostgreSQL doesn't have to guess the overload
+///
+fn gen_call_args(func_def: &PgFunction) -> (String, String) {
+    let pg_args = func_def
+        .arguments
+        .iter()
+        .enumerate()
+        .map(|(x, arg)| {
+            let variadic = if arg.is_variadic { "VARIADIC " } else { "" };
+            format!("{}${}::{}", variadic, x + 1, arg.sql_type)
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+    let rs_args = func_def
+        .arguments
+        .iter()
+        .map(|arg| format!("&{}", &gen_arg_name(&arg.name)))
+        .collect::<Vec<String>>()
+        .join(", ");
+    (pg_args, rs_args)
+}
+
+/// Generate the expression that converts a `row` into the result of a function
+///
+fn gen_row_decoder(database: &BTreeMap<Oid, PgType>, returns: &PgReturn) -> Result<String, String> {
+    match returns {
+        PgReturn::Scalar(typ) => match find_type(database, *typ)? {
+            PgType::Composite { schema, name, .. } => Ok(format!("{}::from_postgres_row(row)?", gen_type_name(schema, name))),
+            _ => Ok(format!("row.get(0)")),
+        },
+        PgReturn::Record(r) => {
+            let foo = r
+                .iter()
+                .map(|ret| format!("row.get(\"{}\")", ret.name))
+                .collect::<Vec<String>>()
+                .join(",");
+            Ok(format!("({})", foo))
+        }
+        _ => Err(format!("Only scalars and records can be decoded from a row")),
+    }
+}
+
 /// Convert a PostgreSQL type name to a safe Rust name
 ///
 fn gen_type_name(schema: &String, name: &String) -> String {
@@ -300,6 +776,36 @@ fn gen_type_name(schema: &String, name: &String) -> String {
     }
 }
 
+/// Name of the enum generated for a lookup table, since the table itself already has a struct
+///
+fn gen_lookup_name(schema: &String, name: &String) -> String {
+    format!("{}Key", gen_type_name(schema, name))
+}
+
+/// Convert a PostgreSQL name to an enum variant that isn't taken yet
+/// Names that can't start a variant get a prefix, and repeated ones a number
+///
+fn gen_variant_name(variants: &mut std::collections::BTreeSet<String>, name: &str, prefix: &str) -> String {
+    let mut variant = name.to_camel_case();
+    if !is_variant_name(&variant) {
+        variant = format!("{}{}", prefix, variant);
+    }
+    let base_name = variant.clone();
+    let mut x = 2;
+    while variants.contains(&variant) {
+        variant = format!("{}{}", base_name, x);
+        x += 1;
+    }
+    variants.insert(variant.clone());
+    variant
+}
+
+/// Whether a name can be used as an enum variant
+///
+fn is_variant_name(name: &str) -> bool {
+    name.starts_with(char::is_alphabetic) && name != "Self"
+}
+
 /// Convert a PostgreSQL function name to a safe Rust name
 ///
 fn gen_function_name(schema: &String, name: &String) -> String {
@@ -323,6 +829,15 @@ fn gen_arg_name(name: &String) -> String {
     name.to_snake_case()
 }
 
+/// Get the definition of a type, partitions are replaced by their parent table
+///
+fn find_type(database: &BTreeMap<Oid, PgType>, oid: Oid) -> Result<&PgType, String> {
+    match database.get(&oid).ok_or(format!("Unknown type #{}", oid))? {
+        PgType::Partition { parent, .. } => find_type(database, *parent),
+        type_def => Ok(type_def),
+    }
+}
+
 /// Generate the Rust definition for a PostgreSQL type
 ///
 fn resolve_fld_type(database: &BTreeMap<Oid, PgType>, oid: Oid) -> Result<TypeCorrespondence, String> {
@@ -334,8 +849,8 @@ fn resolve_fld_type(database: &BTreeMap<Oid, PgType>, oid: Oid) -> Result<TypeCo
             serializable: true,
         }),
         PgType::Composite { schema, name, fields, .. } => {
-            let copyable = fields.iter().all(|f| resolve_fld_type(database, f.typ).unwrap().copyable);
-            let serializable = fields.iter().all(|f| resolve_fld_type(database, f.typ).unwrap().serializable);
+            let copyable = fields.iter().all(|f| resolve_column_type(database, f).unwrap().copyable);
+            let serializable = fields.iter().all(|f| resolve_column_type(database, f).unwrap().serializable);
             Ok(TypeCorrespondence {
                 rs_type: gen_type_name(schema, name),
                 copyable,
@@ -343,6 +858,7 @@ fn resolve_fld_type(database: &BTreeMap<Oid, PgType>, oid: Oid) -> Result<TypeCo
             })
         }
         PgType::Domain { base_type, .. } => resolve_fld_type(database, *base_type),
+        PgType::Partition { parent, .. } => resolve_fld_type(database, *parent),
         PgType::Array { base_type, .. } => {
             let inner = resolve_fld_type(database, *base_type)?;
             Ok(TypeCorrespondence {
@@ -387,6 +903,7 @@ fn resolve_arg_type(database: &BTreeMap<Oid, PgType>, oid: Oid) -> Result<String
                 Ok(format!("&{}", inner.rs_type))
             }
         }
+        PgType::Partition { parent, .. } => resolve_arg_type(database, *parent),
         PgType::Array { base_type, .. } => {
             let inner = resolve_arg_type(database, *base_type)?;
             Ok(format!("&[{}]", inner))
@@ -399,6 +916,49 @@ fn resolve_arg_type(database: &BTreeMap<Oid, PgType>, oid: Oid) -> Result<String
     }
 }
 
+/// Generate the Rust definition for a column of a table or composite, which may use the enum of a lookup table
+///
+fn resolve_column_type(database: &BTreeMap<Oid, PgType>, field: &PgField) -> Result<TypeCorrespondence, String> {
+    match field.lookup {
+        Some(oid) => match find_type(database, oid)? {
+            PgType::Composite { schema, name, .. } => Ok(TypeCorrespondence {
+                rs_type: gen_lookup_name(schema, name),
+                copyable: true,
+                serializable: true,
+            }),
+            _ => Err(format!("Unknown lookup table #{}", oid)),
+        },
+        None => resolve_fld_type(database, field.typ),
+    }
+}
+
+/// Generate the Rust definition for an argument given as the value of a column
+///
+fn resolve_column_arg_type(database: &BTreeMap<Oid, PgType>, field: &PgField) -> Result<String, String> {
+    match field.lookup {
+        Some(_) => Ok(resolve_column_type(database, field)?.rs_type),
+        None => resolve_arg_type(database, field.typ),
+    }
+}
+
+/// Generate the Rust definition for a single row of a function result
+///
+fn resolve_row_type(database: &BTreeMap<Oid, PgType>, returns: &PgReturn) -> Result<String, String> {
+    match returns {
+        PgReturn::Void => Ok(format!("()")),
+        PgReturn::Scalar(typ) => resolve_ret_type(&database, *typ),
+        PgReturn::Record(r) => {
+            let foo = r
+                .iter()
+                .map(|field| resolve_ret_type(&database, field.typ))
+                .collect::<Result<Vec<_>, _>>()?
+                .join(", ");
+            Ok(format!("({})", foo))
+        }
+        PgReturn::Cursor(_) => Err(format!("Cursor functions are generated separately")),
+    }
+}
+
 /// Generate the Rust definition for a function result
 ///
 fn resolve_ret_type(database: &BTreeMap<Oid, PgType>, oid: Oid) -> Result<String, String> {
@@ -407,6 +967,7 @@ fn resolve_ret_type(database: &BTreeMap<Oid, PgType>, oid: Oid) -> Result<String
         PgType::Enum { schema, name, .. } => Ok(gen_type_name(schema, name)),
         PgType::Composite { schema, name, .. } => Ok(gen_type_name(schema, name)),
         PgType::Domain { base_type, .. } => resolve_ret_type(database, *base_type),
+        PgType::Partition { parent, .. } => resolve_ret_type(database, *parent),
         PgType::Array { base_type, .. } => {
             let inner = resolve_ret_type(database, *base_type)?;
             Ok(format!("Vec<{}>", inner))
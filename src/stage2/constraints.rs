@@ -0,0 +1,155 @@
+use super::*;
+use std::collections::BTreeSet;
+
+// --------------------------------------------------------------------------------------------------------------------
+// Public functions
+// --------------------------------------------------------------------------------------------------------------------
+
+/// Generate the enum of the named constraints of a table, to tell which one a failed statement violated
+/// Unique indexes that don't implement a constraint are included, since their violations report the index name
+///
+pub fn gen_constraint_error(
+    scope: &mut codegen::Scope,
+    database: &BTreeMap<Oid, PgType>,
+    options: &Options,
+    oid: Oid,
+    type_def: &PgType,
+) -> Result<(), String> {
+    let (schema, name, indexes, constraints, partition_key) = match type_def {
+        PgType::Composite {
+            schema,
+            name,
+            is_table: true,
+            indexes,
+            constraints,
+            partition_key,
+            ..
+        } => (schema, name, indexes, constraints, partition_key),
+        _ => return Ok(()),
+    };
+    let mut all_constraints = constraints.iter().map(|c| (&c.name, c.kind, &c.partition_names)).collect::<Vec<_>>();
+    for index in indexes.iter().filter(|i| i.is_unique && i.constraint.is_none()) {
+        all_constraints.push((&index.name, PgConstraintKind::Unique, &index.partition_names));
+    }
+    if all_constraints.is_empty() {
+        return Ok(());
+    }
+    let rs_name = format!("{}ConstraintError", gen_type_name(schema, name));
+    let crate_name = match options.flavor {
+        Flavor::Sync => "postgres",
+        Flavor::Async => "tokio_postgres",
+    };
+
+    // Constraint names usually start with the table name, which is redundant in the variants
+    let mut variants = BTreeSet::new();
+    let mut constraint_variants = Vec::new();
+    for (constraint, kind, partition_names) in all_constraints {
+        let short_name = constraint.strip_prefix(&format!("{}_", name)).unwrap_or(constraint).to_camel_case();
+        let variant = if is_variant_name(&short_name) && !variants.contains(&short_name) {
+            variants.insert(short_name.clone());
+            short_name
+        } else {
+            gen_variant_name(&mut variants, constraint, "Constraint")
+        };
+        constraint_variants.push((constraint, kind, partition_names, variant));
+    }
+
+    // Create the new enum definition
+    let new_enum = scope.new_enum(&rs_name);
+    new_enum.vis("pub");
+    new_enum.derive("Debug");
+    new_enum.derive("Copy");
+    new_enum.derive("Clone");
+    new_enum.derive("PartialEq");
+    new_enum.derive("Eq");
+    for (_, _, _, variant) in &constraint_variants {
+        new_enum.new_variant(variant);
+    }
+
+    // Name of the constraint, and the SQLSTATE reported when it's violated
+    let mut name_func = codegen::Function::new("constraint_name");
+    name_func.vis("pub");
+    name_func.arg_ref_self();
+    name_func.ret("&'static str");
+    let mut state_func = codegen::Function::new("sql_state");
+    state_func.vis("pub");
+    state_func.arg_ref_self();
+    state_func.ret(format!("{}::error::SqlState", crate_name));
+    let mut name_block = codegen::Block::new("match self");
+    let mut state_block = codegen::Block::new("match self");
+    for (constraint, kind, _, variant) in &constraint_variants {
+        let state = match kind {
+            PgConstraintKind::PrimaryKey | PgConstraintKind::Unique => "UNIQUE_VIOLATION",
+            PgConstraintKind::ForeignKey => "FOREIGN_KEY_VIOLATION",
+            PgConstraintKind::Check => "CHECK_VIOLATION",
+            PgConstraintKind::Exclusion => "EXCLUSION_VIOLATION",
+        };
+        name_block.line(format!("Self::{} => {:?},", variant, constraint));
+        state_block.line(format!("Self::{} => {}::error::SqlState::{},", variant, crate_name, state));
+    }
+    name_func.push_block(name_block);
+    state_func.push_block(state_block);
+
+    // Find the violated constraint, if the error is a violation of one of them
+    // Rows of partitioned tables are stored in the partitions, so the errors report the partition as the table,
+    // and the unique constraints by the name of their index in the partition
+    let mut tables = vec![(schema, name)];
+    if partition_key.is_some() {
+        tables.extend(find_partitions(database, oid));
+    }
+    let mut from_func = codegen::Function::new("from_error");
+    from_func.vis("pub");
+    from_func.arg("error", format!("&{}::Error", crate_name));
+    from_func.ret("Option<Self>");
+    from_func.line("let error = error.as_db_error()?;");
+    let mut block = codegen::Block::new("match (error.schema(), error.table())");
+    let patterns = tables
+        .iter()
+        .map(|(schema, name)| format!("(Some({:?}), Some({:?}))", schema, name))
+        .collect::<Vec<_>>();
+    block.line(format!("{} => {{}}", patterns.join(" | ")));
+    block.line("_ => return None,");
+    from_func.push_block(block);
+    let mut block = codegen::Block::new("let constraint = match error.constraint()?");
+    let mut names = BTreeSet::new();
+    for (constraint, _, partition_names, variant) in &constraint_variants {
+        let patterns = std::iter::once(*constraint)
+            .chain(partition_names.iter())
+            .filter(|x| names.insert(x.to_string()))
+            .map(|x| format!("{:?}", x))
+            .collect::<Vec<_>>();
+        if !patterns.is_empty() {
+            block.line(format!("{} => Self::{},", patterns.join(" | "), variant));
+        }
+    }
+    block.line("_ => return None,");
+    block.after(";");
+    from_func.push_block(block);
+    from_func.line("if error.code() == &constraint.sql_state() { Some(constraint) } else { None }");
+
+    // Done
+    let new_impl = scope.new_impl(&rs_name);
+    new_impl.push_fn(name_func);
+    new_impl.push_fn(state_func);
+    new_impl.push_fn(from_func);
+    Ok(())
+}
+
+// --------------------------------------------------------------------------------------------------------------------
+// Private functions
+// --------------------------------------------------------------------------------------------------------------------
+
+/// Schema and name of the partitions of a table, and of their own partitions
+///
+fn find_partitions(database: &BTreeMap<Oid, PgType>, oid: Oid) -> Vec<(&String, &String)> {
+    let mut partitions = Vec::new();
+    for (partition_oid, type_def) in database {
+        if let PgType::Partition { schema, name, parent } = type_def {
+            if *parent == oid {
+                partitions.push((schema, name));
+                partitions.extend(find_partitions(database, *partition_oid));
+            }
+        }
+    }
+    partitions
+}
@@ -0,0 +1,94 @@
+use super::*;
+
+// --------------------------------------------------------------------------------------------------------------------
+// Public functions
+// --------------------------------------------------------------------------------------------------------------------
+
+/// Generate a module with the metadata of a table or view, and one marker type per column to build typed queries
+///
+pub fn gen_filters(scope: &mut codegen::Scope, database: &BTreeMap<Oid, PgType>, type_def: &PgType) -> Result<(), String> {
+    let (schema, name, fields, primary_key) = match type_def {
+        PgType::Composite {
+            schema,
+            name,
+            fields,
+            is_table,
+            is_view,
+            primary_key,
+            ..
+        } if *is_table || *is_view => (schema, name, fields, primary_key),
+        _ => return Ok(()),
+    };
+    let rs_name = gen_type_name(schema, name);
+    let module_name = gen_function_name(schema, name);
+    let sql_name = format!("{}.{}", quote_ident(schema), quote_ident(name));
+    let select = format!("SELECT {} FROM {}", fields.iter().map(|f| quote_ident(&f.name)).collect::<Vec<_>>().join(", "), sql_name);
+
+    // Constants and columns go in a module named like the table, the columns are named like the struct fields
+    let new_module = scope.new_module(&module_name);
+    new_module.vis("pub");
+    new_module.import("super", "*");
+    let constants = [
+        format!("pub const SCHEMA: &str = {:?};", schema),
+        format!("pub const NAME: &str = {:?};", name),
+        format!("pub const SQL_NAME: &str = {:?};", sql_name),
+        format!("pub const COLUMNS: &[&str] = &{:?};", fields.iter().map(|f| &f.name).collect::<Vec<_>>()),
+        format!("pub const PRIMARY_KEY: &[&str] = &{:?};", primary_key),
+        format!("pub const SELECT: &str = {:?};", select),
+    ];
+    new_module.scope().raw(&constants.join("\n"));
+    for field in fields {
+        let column_name = gen_fld_name(&field.name);
+        let value_type = resolve_column_type(database, field)?.rs_type;
+
+        let new_struct = new_module.new_struct(&column_name);
+        new_struct.vis("pub");
+        new_struct.derive("Debug");
+        new_struct.derive("Clone");
+        new_struct.derive("Copy");
+        new_struct.r#macro("#[allow(non_camel_case_types)]");
+
+        let new_impl = new_module.new_impl(&column_name);
+        new_impl.impl_trait("Column");
+        new_impl.associate_type("Table", &rs_name);
+        new_impl.associate_type("Value", value_type);
+        let new_func = new_impl.new_fn("sql_name");
+        new_func.ret("&'static str");
+        new_func.line(format!("{:?}", quote_ident(&field.name)));
+    }
+
+    // The same constants, available through the struct
+    let trait_impl = [
+        format!("impl TableMeta for {} {{", rs_name),
+        format!("    const SCHEMA: &'static str = {}::SCHEMA;", module_name),
+        format!("    const NAME: &'static str = {}::NAME;", module_name),
+        format!("    const SQL_NAME: &'static str = {}::SQL_NAME;", module_name),
+        format!("    const COLUMNS: &'static [&'static str] = {}::COLUMNS;", module_name),
+        format!("    const PRIMARY_KEY: &'static [&'static str] = {}::PRIMARY_KEY;", module_name),
+        format!("    const SELECT: &'static str = {}::SELECT;", module_name),
+        "}".to_string(),
+    ];
+    scope.raw(&trait_impl.join("\n"));
+
+    // Done
+    Ok(())
+}
+
+/// Generate the function that runs a typed query, which depends on the client
+///
+pub fn gen_query_load(scope: &mut codegen::Scope, options: &Options) {
+    let new_impl = scope.new_impl("Query");
+    new_impl.generic("T: TableMeta + FromPostgresRow");
+    new_impl.target_generic("T");
+
+    let new_func = new_impl.new_fn("load");
+    new_func.vis("pub");
+    new_func.set_async(options.flavor == Flavor::Async);
+    new_func.arg_ref_self();
+    new_func.arg("db", gen_client_type(options));
+    new_func.ret("PgResult<Vec<T>>");
+    new_func.line("let (sql, params) = self.to_sql();");
+    new_func.line(format!("let query = db.query(sql.as_str(), &params){}?;", gen_await(options)));
+    new_func.line("let result: Result<Vec<T>, _> = query.into_iter().map(T::from_postgres_row).collect();");
+    new_func.line("Ok(result?)");
+}
@@ -0,0 +1,77 @@
+use super::*;
+
+// --------------------------------------------------------------------------------------------------------------------
+// Public functions
+// --------------------------------------------------------------------------------------------------------------------
+
+/// Generate the enum of a lookup table, with one variant per row and the key of the row as the discriminant
+/// The enum is converted to and from the integer key, so it can replace the key in the columns referencing the table
+///
+pub fn gen_lookup(scope: &mut codegen::Scope, database: &BTreeMap<Oid, PgType>, options: &Options, lookup: &PgLookup) -> Result<(), String> {
+    let PgLookup {
+        schema,
+        name,
+        key_type,
+        values,
+    } = lookup;
+    let rs_name = gen_lookup_name(schema, name);
+    let rs_key = resolve_fld_type(database, *key_type)?.rs_type;
+    let types = gen_types_path(options);
+
+    // Create the new enum definition, like the ones of PostgreSQL enums but with explicit discriminants
+    let new_enum = scope.new_enum(&rs_name);
+    new_enum.vis("pub");
+    new_enum.derive("Debug");
+    new_enum.derive("Copy");
+    new_enum.derive("Clone");
+    new_enum.derive("PartialEq");
+    new_enum.derive("Eq");
+    new_enum.derive("PartialOrd");
+    new_enum.derive("Ord");
+    new_enum.derive("Serialize");
+    new_enum.derive("Deserialize");
+    new_enum.derive("FromFormField");
+    new_enum.derive("Enum");
+    new_enum.r#macro(&format!("#[repr({})]", rs_key));
+    for value in values {
+        new_enum.new_variant(&format!("{} = {}", value.label.to_camel_case(), value.key));
+    }
+
+    // Convert to the key
+    let to_sql = [
+        format!("impl {}::ToSql for {} {{", types, rs_name),
+        format!(
+            "    fn to_sql(&self, ty: &{0}::Type, out: &mut bytes::BytesMut) -> Result<{0}::IsNull, Box<dyn std::error::Error + Sync + Send>> {{",
+            types
+        ),
+        format!("        {}::ToSql::to_sql(&(*self as {}), ty, out)", types, rs_key),
+        "    }".to_string(),
+        format!("    fn accepts(ty: &{}::Type) -> bool {{", types),
+        format!("        <{} as {}::ToSql>::accepts(ty)", rs_key, types),
+        "    }".to_string(),
+        format!("    {}::to_sql_checked!();", types),
+        "}".to_string(),
+    ];
+    scope.raw(&to_sql.join("\n"));
+
+    // Convert from the key, rows added after generating the bindings are an error
+    let mut from_sql = vec![
+        format!("impl<'a> {}::FromSql<'a> for {} {{", types, rs_name),
+        format!("    fn from_sql(ty: &{}::Type, raw: &'a [u8]) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {{", types),
+        format!("        match <{} as {}::FromSql>::from_sql(ty, raw)? {{", rs_key, types),
+    ];
+    for value in values {
+        from_sql.push(format!("            {} => Ok(Self::{}),", value.key, value.label.to_camel_case()));
+    }
+    from_sql.push(format!("            x => Err(format!(\"Unknown {{}} key {{}}\", {:?}, x).into()),", name));
+    from_sql.push("        }".to_string());
+    from_sql.push("    }".to_string());
+    from_sql.push(format!("    fn accepts(ty: &{}::Type) -> bool {{", types));
+    from_sql.push(format!("        <{} as {}::FromSql>::accepts(ty)", rs_key, types));
+    from_sql.push("    }".to_string());
+    from_sql.push("}".to_string());
+    scope.raw(&from_sql.join("\n"));
+
+    // Done
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use super::*;
+
+// --------------------------------------------------------------------------------------------------------------------
+// Public functions
+// --------------------------------------------------------------------------------------------------------------------
+
+/// Generate the functions to read and move a sequence, typed like its values
+/// `nextval` and friends always work with bigint, so the results are cast back to the type of the sequence
+///
+pub fn gen_sequence(database: &BTreeMap<Oid, PgType>, options: &Options, seq_def: &PgSequence) -> Result<Vec<codegen::Function>, String> {
+    let rs_type = resolve_ret_type(database, seq_def.typ)?;
+    let rs_name = gen_function_name(&seq_def.schema, &seq_def.name);
+    let sql_name = format!("{}.{}", quote_ident(&seq_def.schema), quote_ident(&seq_def.name));
+    let regclass = format!("'{}'", sql_name.replace('\'', "''"));
+
+    // Next value
+    let mut nextval_func = gen_sequence_function(options, &format!("nextval_{}", rs_name));
+    nextval_func.ret(format!("PgResult<{}>", rs_type));
+    let sql = format!("SELECT nextval({})::{}", regclass, seq_def.sql_type);
+    gen_single_value(&mut nextval_func, options, &sql, "");
+
+    // Last value given in this session
+    let mut currval_func = gen_sequence_function(options, &format!("currval_{}", rs_name));
+    currval_func.ret(format!("PgResult<{}>", rs_type));
+    let sql = format!("SELECT currval({})::{}", regclass, seq_def.sql_type);
+    gen_single_value(&mut currval_func, options, &sql, "");
+
+    // Move the sequence, if `is_called` the next value will be the one after `value`
+    let mut setval_func = gen_sequence_function(options, &format!("setval_{}", rs_name));
+    setval_func.arg("value", &rs_type);
+    setval_func.arg("is_called", "bool");
+    setval_func.ret(format!("PgResult<{}>", rs_type));
+    let sql = format!("SELECT setval({}, $1::{}, $2)::{}", regclass, seq_def.sql_type, seq_def.sql_type);
+    gen_single_value(&mut setval_func, options, &sql, "&value, &is_called");
+
+    // Several values at once, to allocate the ids of many rows in a single round trip
+    let mut nextvals_func = gen_sequence_function(options, &format!("nextvals_{}", rs_name));
+    nextvals_func.arg("n", "i64");
+    nextvals_func.ret(format!("PgResult<Vec<{}>>", rs_type));
+    let sql = format!("SELECT nextval({})::{} FROM generate_series(1, $1::int8)", regclass, seq_def.sql_type);
+    nextvals_func.line(format!("let query = db.query({:?}, &[&n]){}?;", sql, gen_await(options)));
+    nextvals_func.line("let result = query.into_iter().map(|row| row.get(0)).collect();");
+    nextvals_func.line("Ok(result)");
+
+    Ok(vec![nextval_func, currval_func, setval_func, nextvals_func])
+}
+
+// --------------------------------------------------------------------------------------------------------------------
+// Private functions
+// --------------------------------------------------------------------------------------------------------------------
+
+/// Create a new public function over a client, async if needed
+///
+fn gen_sequence_function(options: &Options, name: &str) -> codegen::Function {
+    let mut new_func = codegen::Function::new(name);
+    new_func.vis("pub");
+    new_func.set_async(options.flavor == Flavor::Async);
+    new_func.arg("db", gen_client_type(options));
+    new_func
+}
+
+/// Run a query returning a single value
+///
+fn gen_single_value(new_func: &mut codegen::Function, options: &Options, sql: &str, rs_args: &str) {
+    new_func.line(format!("let query = db.query({:?}, &[{}]){}?;", sql, rs_args, gen_await(options)));
+    new_func.line("let row = query.into_iter().next().ok_or(PgError::KeyNotFound)?;");
+    new_func.line("Ok(row.get(0))");
+}
@@ -0,0 +1,1235 @@
+use super::*;
+use std::collections::BTreeSet;
+
+// --------------------------------------------------------------------------------------------------------------------
+// Public functions
+// --------------------------------------------------------------------------------------------------------------------
+
+/// Generate the functions to read and write the rows of a table or view, as methods of its struct
+/// Views only get the functions for the statements they support, so read-only views are left alone
+///
+pub fn gen_table(scope: &mut codegen::Scope, database: &BTreeMap<Oid, PgType>, options: &Options, oid: Oid, type_def: &PgType) -> Result<(), String> {
+    let table = match Table::new(oid, type_def)? {
+        Some(x) => x,
+        None => return Ok(()),
+    };
+
+    // Rows can be inserted without a key, but the other functions need one
+    let mut names = BTreeSet::new();
+    let mut functions = Vec::new();
+    if table.is_insertable {
+        functions.push(gen_insert(&mut names, options, &table)?);
+    }
+    if !table.primary_key.is_empty() {
+        functions.push(gen_get_by_pk(&mut names, database, options, &table)?);
+        if table.is_updatable {
+            if let Some(new_func) = gen_update_by_pk(&mut names, options, &table)? {
+                functions.push(new_func);
+            }
+        }
+        if table.is_deletable {
+            functions.push(gen_delete_by_pk(&mut names, database, options, &table)?);
+        }
+    }
+    functions.extend(gen_find_by(&mut names, database, options, &table)?);
+    functions.extend(gen_page_by(&mut names, database, options, &table)?);
+    functions.extend(gen_tree(&mut names, database, options, &table)?);
+    if has_children(database, &table) {
+        functions.push(gen_select_all(&mut names, options, &table, false));
+        functions.push(gen_select_all(&mut names, options, &table, true));
+    }
+    if table.is_insertable && !table.is_view {
+        functions.push(gen_copy_in(&mut names, database, options, &table)?);
+    }
+    functions.push(gen_copy_out(&mut names, database, options, &table)?);
+
+    // Relations go last, since their names are derived from the schema and may clash with the other methods
+    functions.extend(gen_relations(&mut names, database, options, &table)?);
+
+    // All of them go in the same impl block
+    if !functions.is_empty() {
+        let new_impl = scope.new_impl(&table.rs_name);
+        for new_func in functions {
+            new_impl.push_fn(new_func);
+        }
+    }
+
+    // Rows of a child table can be used as rows of its parents
+    for parent_oid in table.parents {
+        if let Some(type_def) = database.get(parent_oid) {
+            if let Some(parent) = Table::new(*parent_oid, type_def)? {
+                if let Err(e) = gen_parent_conversion(scope, database, &table, &parent) {
+                    eprintln!("{}", e);
+                }
+            }
+        }
+    }
+
+    // Materialized views can be refreshed
+    if table.is_materialized {
+        scope.push_fn(gen_refresh(options, &table));
+    }
+
+    // Struct to insert new rows, and the columns to update on conflicts
+    if table.is_insertable {
+        gen_column_enum(scope, &table);
+        gen_insert_struct(scope, database, options, &table)?;
+    }
+
+    // Struct to update some columns of a row
+    if table.is_updatable && !table.primary_key.is_empty() {
+        gen_patch_struct(scope, database, options, &table)?;
+    }
+
+    // Done
+    Ok(())
+}
+
+/// Whether the patch struct of a table deserializes a nullable column, which needs the `deserialize_some` helper
+///
+pub fn needs_deserialize_some(database: &BTreeMap<Oid, PgType>, oid: Oid, type_def: &PgType) -> Result<bool, String> {
+    let table = match Table::new(oid, type_def)? {
+        Some(x) => x,
+        None => return Ok(false),
+    };
+    if !table.is_updatable || table.primary_key.is_empty() {
+        return Ok(false);
+    }
+    let fields = gen_patch_fields(&table);
+    Ok(fields.iter().any(|f| f.is_nullable) && is_serializable(database, &fields)?)
+}
+
+// --------------------------------------------------------------------------------------------------------------------
+// Private functions
+// --------------------------------------------------------------------------------------------------------------------
+
+/// Everything needed to generate the functions of a table or view
+struct Table<'a> {
+    oid: Oid,
+    schema: &'a String,
+    name: &'a String,
+    rs_name: String,
+    sql_name: String,
+    fields: &'a Vec<PgField>,
+    primary_key: Vec<&'a PgField>,
+    indexes: &'a Vec<PgIndex>,
+    foreign_keys: &'a Vec<PgForeignKey>,
+    parents: &'a Vec<Oid>,
+    is_view: bool,
+    is_materialized: bool,
+    is_insertable: bool,
+    is_updatable: bool,
+    is_deletable: bool,
+}
+
+impl<'a> Table<'a> {
+    /// Get the table definition, if the type is a table or a view
+    fn new(oid: Oid, type_def: &'a PgType) -> Result<Option<Self>, String> {
+        match type_def {
+            PgType::Composite {
+                schema,
+                name,
+                is_table,
+                is_view,
+                is_materialized,
+                is_insertable,
+                is_updatable,
+                is_deletable,
+                fields,
+                primary_key,
+                indexes,
+                foreign_keys,
+                parents,
+                ..
+            } if *is_table || *is_view => {
+                let primary_key = primary_key
+                    .iter()
+                    .map(|key| fields.iter().find(|f| &f.name == key).ok_or(format!("Unknown key column {}", key)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Some(Self {
+                    oid,
+                    schema,
+                    name,
+                    rs_name: gen_type_name(schema, name),
+                    sql_name: format!("{}.{}", quote_ident(schema), quote_ident(name)),
+                    fields,
+                    primary_key,
+                    indexes,
+                    foreign_keys,
+                    parents,
+                    is_view: *is_view,
+                    is_materialized: *is_materialized,
+                    is_insertable: *is_insertable,
+                    is_updatable: *is_updatable,
+                    is_deletable: *is_deletable,
+                }))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Get the fields of some columns, in the given order
+    fn columns(&self, names: &[String]) -> Result<Vec<&'a PgField>, String> {
+        names
+            .iter()
+            .map(|c| {
+                self.fields
+                    .iter()
+                    .find(|f| &f.name == c)
+                    .ok_or(format!("Unknown column {} in {}", c, self.sql_name))
+            })
+            .collect()
+    }
+}
+
+/// Insert a row, and get it back as stored in the database
+/// Every column is inserted as is, even identity ones, but generated columns are always computed by the database
+///
+fn gen_insert(names: &mut BTreeSet<String>, options: &Options, table: &Table) -> Result<codegen::Function, String> {
+    let mut new_func = gen_table_method(names, options, "insert");
+    new_func.arg_ref_self();
+    new_func.arg("db", gen_client_type(options));
+    new_func.ret("PgResult<Self>");
+
+    let fields = table.fields.iter().filter(|f| !f.is_generated).collect::<Vec<_>>();
+    let columns = fields.iter().map(|f| quote_ident(&f.name)).collect::<Vec<_>>().join(", ");
+    let values = (0..fields.len()).map(|x| format!("${}", x + 1)).collect::<Vec<_>>().join(", ");
+    let overriding = if fields.iter().any(|f| f.identity == PgIdentity::Always) {
+        " OVERRIDING SYSTEM VALUE"
+    } else {
+        ""
+    };
+    let sql = format!("INSERT INTO {} ({}){} VALUES ({}) RETURNING *", table.sql_name, columns, overriding, values);
+    gen_single_row(&mut new_func, options, &sql, &gen_self_args(&fields));
+    Ok(new_func)
+}
+
+/// Generate the struct used to insert new rows, which leaves out the columns always filled by the database
+/// Columns with a default value are optional, and the default is used when they are not provided
+///
+fn gen_insert_struct(scope: &mut codegen::Scope, database: &BTreeMap<Oid, PgType>, options: &Options, table: &Table) -> Result<(), String> {
+    let fields = table.fields.iter().filter(|f| is_writable(f)).collect::<Vec<_>>();
+
+    // Create the new struct definition
+    let new_struct = scope.new_struct(&format!("New{}", table.rs_name));
+    new_struct.vis("pub");
+    new_struct.derive("Debug");
+    new_struct.derive("Clone");
+
+    // Generate the struct fields
+    let mut serializable = true;
+    for field in &fields {
+        let foo = resolve_column_type(database, field)?;
+        serializable = serializable & foo.serializable;
+        let mut rs_type = if field.is_nullable {
+            format!("Option<{}>", foo.rs_type)
+        } else {
+            foo.rs_type
+        };
+        if has_default(field) {
+            rs_type = format!("Option<{}>", rs_type);
+        }
+        new_struct.field(&format!("pub {}", gen_fld_name(&field.name)), rs_type);
+    }
+    if serializable {
+        new_struct.derive("Serialize");
+        new_struct.derive("Deserialize");
+    }
+
+    // Insert function
+    let mut new_func = gen_method(options, "insert");
+    new_func.arg_ref_self();
+    new_func.arg("db", gen_client_type(options));
+    new_func.ret(format!("PgResult<{}>", table.rs_name));
+    gen_new_row_values(&mut new_func, options, &fields);
+    new_func.line(format!("let sql = format!({:?}, values_sql);", format!("INSERT INTO {} {{}} RETURNING *", table.sql_name)));
+    new_func.line(format!("let query = db.query(sql.as_str(), &values){}?;", gen_await(options)));
+    new_func.line("let row = query.into_iter().next().ok_or(PgError::KeyNotFound)?;");
+    new_func.line(format!("Ok({}::from_postgres_row(row)?)", table.rs_name));
+
+    // Upsert functions, one per unique constraint or index
+    let mut functions = vec![new_func];
+    // Without a writable column there's nothing to update
+    for index in table.indexes.iter().filter(|i| i.is_unique && !i.is_partial && !i.has_expressions) {
+        if !fields.is_empty() {
+            functions.push(gen_upsert(options, table, &fields, index, true));
+        }
+        functions.push(gen_upsert(options, table, &fields, index, false));
+    }
+
+    // Done
+    let new_impl = scope.new_impl(&format!("New{}", table.rs_name));
+    for new_func in functions {
+        new_impl.push_fn(new_func);
+    }
+    Ok(())
+}
+
+/// Insert a new row, or update the existing one if it conflicts with a unique constraint or index
+/// Only the given columns are updated, if there are none the existing row is returned as is.
+/// The DO NOTHING variant returns the new row, or nothing if there was a conflict
+///
+fn gen_upsert(options: &Options, table: &Table, fields: &[&PgField], index: &PgIndex, do_update: bool) -> codegen::Function {
+    let index_name = index.constraint.as_ref().unwrap_or(&index.name);
+    let func_name = if do_update {
+        format!("upsert_on_{}", index_name.to_snake_case())
+    } else {
+        format!("upsert_on_{}_do_nothing", index_name.to_snake_case())
+    };
+    let mut new_func = gen_method(options, &func_name);
+    new_func.arg_ref_self();
+    new_func.arg("db", gen_client_type(options));
+    if do_update {
+        new_func.arg("update", format!("&[{}Column]", table.rs_name));
+        new_func.ret(format!("PgResult<{}>", table.rs_name));
+    } else {
+        new_func.ret(format!("PgResult<Option<{}>>", table.rs_name));
+    }
+    gen_new_row_values(&mut new_func, options, fields);
+
+    // Constraints are named, otherwise the index is found from its columns
+    let conflict = match &index.constraint {
+        Some(constraint) => format!("ON CONFLICT ON CONSTRAINT {}", quote_ident(constraint)),
+        None => format!("ON CONFLICT ({})", index.columns.iter().map(|c| quote_ident(c)).collect::<Vec<_>>().join(", ")),
+    };
+    if do_update {
+        // Without columns to update, a writable column is set to the value it already has, so the row is returned as is
+        let unchanged = match index.columns.iter().find(|c| fields.iter().any(|f| &f.name == *c)) {
+            Some(column) => format!("{0} = EXCLUDED.{0}", quote_ident(column)),
+            None => format!("{0} = {1}.{0}", quote_ident(&fields[0].name), table.sql_name),
+        };
+        new_func.line("let mut assignments = update.iter().map(|c| format!(\"{0} = EXCLUDED.{0}\", c.quoted_name())).collect::<Vec<_>>();");
+        new_func.line(format!("if assignments.is_empty() {{ assignments.push({:?}.to_string()); }}", unchanged));
+        new_func.line(format!(
+            "let sql = format!({:?}, values_sql, assignments.join(\", \"));",
+            format!("INSERT INTO {} {{}} {} DO UPDATE SET {{}} RETURNING *", table.sql_name, conflict)
+        ));
+        new_func.line(format!("let query = db.query(sql.as_str(), &values){}?;", gen_await(options)));
+        new_func.line("let row = query.into_iter().next().ok_or(PgError::KeyNotFound)?;");
+        new_func.line(format!("Ok({}::from_postgres_row(row)?)", table.rs_name));
+    } else {
+        new_func.line(format!(
+            "let sql = format!({:?}, values_sql);",
+            format!("INSERT INTO {} {{}} {} DO NOTHING RETURNING *", table.sql_name, conflict)
+        ));
+        new_func.line(format!("let query = db.query(sql.as_str(), &values){}?;", gen_await(options)));
+        let mut block = codegen::Block::new("match query.into_iter().next()");
+        block.line(format!("Some(row) => Ok(Some({}::from_postgres_row(row)?)),", table.rs_name));
+        block.line("None => Ok(None),");
+        new_func.push_block(block);
+    }
+    new_func
+}
+
+/// Generate the enum listing the columns of a table that can be updated on conflicts
+/// Generated columns and the identities always filled by the database can't be written, so they are left out
+///
+fn gen_column_enum(scope: &mut codegen::Scope, table: &Table) {
+    let enum_name = format!("{}Column", table.rs_name);
+    let mut variants = BTreeSet::new();
+    let columns = table
+        .fields
+        .iter()
+        .filter(|f| is_writable(f))
+        .map(|f| (&f.name, gen_variant_name(&mut variants, &f.name, "Column")))
+        .collect::<Vec<_>>();
+    if columns.is_empty() {
+        return;
+    }
+    let new_enum = scope.new_enum(&enum_name);
+    new_enum.vis("pub");
+    new_enum.derive("Debug");
+    new_enum.derive("Copy");
+    new_enum.derive("Clone");
+    new_enum.derive("PartialEq");
+    new_enum.derive("Eq");
+    for (_, variant) in &columns {
+        new_enum.new_variant(variant);
+    }
+
+    // Name of the column, as is and quoted to be used in a query
+    let mut name_func = codegen::Function::new("name");
+    name_func.vis("pub");
+    name_func.arg_ref_self();
+    name_func.ret("&'static str");
+    let mut quoted_func = codegen::Function::new("quoted_name");
+    quoted_func.vis("pub");
+    quoted_func.arg_ref_self();
+    quoted_func.ret("&'static str");
+    let mut name_block = codegen::Block::new("match self");
+    let mut quoted_block = codegen::Block::new("match self");
+    for (name, variant) in &columns {
+        name_block.line(format!("Self::{} => {:?},", variant, name));
+        quoted_block.line(format!("Self::{} => {:?},", variant, quote_ident(name)));
+    }
+    name_func.push_block(name_block);
+    quoted_func.push_block(quoted_block);
+    let new_impl = scope.new_impl(&enum_name);
+    new_impl.push_fn(name_func);
+    new_impl.push_fn(quoted_func);
+}
+
+/// Collect the columns and values of a new row into `values_sql` and `values`
+/// The list of columns is only known at runtime, since the ones with a default value may be missing
+///
+fn gen_new_row_values(new_func: &mut codegen::Function, options: &Options, fields: &[&PgField]) {
+    new_func.line("let mut columns: Vec<&str> = Vec::new();");
+    new_func.line(format!("let mut values: Vec<{}> = Vec::new();", gen_param_type(options)));
+    for field in fields {
+        let rs_name = gen_fld_name(&field.name);
+        if has_default(field) {
+            let mut block = codegen::Block::new(&format!("if let Some(value) = &self.{}", rs_name));
+            block.line(format!("columns.push({:?});", quote_ident(&field.name)));
+            block.line("values.push(value);");
+            new_func.push_block(block);
+        } else {
+            new_func.line(format!("columns.push({:?});", quote_ident(&field.name)));
+            new_func.line(format!("values.push(&self.{});", rs_name));
+        }
+    }
+    new_func.line("let placeholders = (1..=values.len()).map(|x| format!(\"${}\", x)).collect::<Vec<_>>().join(\", \");");
+    new_func.line(
+        "let values_sql = if columns.is_empty() { \"DEFAULT VALUES\".to_string() } else { format!(\"({}) VALUES ({})\", columns.join(\", \"), placeholders) };",
+    );
+}
+
+/// Generate the struct used to update some columns of a row, every column but the key is optional
+/// Nullable columns have three states: not provided, set to NULL, and set to a value
+///
+fn gen_patch_struct(scope: &mut codegen::Scope, database: &BTreeMap<Oid, PgType>, options: &Options, table: &Table) -> Result<(), String> {
+    let fields = gen_patch_fields(table);
+    if fields.is_empty() {
+        return Ok(());
+    }
+
+    // Create the new struct definition
+    let struct_name = format!("{}Patch", table.rs_name);
+    let new_struct = scope.new_struct(&struct_name);
+    new_struct.vis("pub");
+    new_struct.derive("Debug");
+    new_struct.derive("Clone");
+    new_struct.derive("Default");
+
+    // Generate the struct fields
+    // When deserializing, a missing field must not be confused with a null one
+    let serializable = is_serializable(database, &fields)?;
+    for field in &fields {
+        let foo = resolve_column_type(database, field)?;
+        let rs_name = gen_fld_name(&field.name);
+        if field.is_nullable {
+            let mut fld = codegen::Field::new(&format!("pub {}", rs_name), format!("Option<Option<{}>>", foo.rs_type));
+            if serializable {
+                fld.annotation(vec!["#[serde(default, deserialize_with = \"deserialize_some\")]"]);
+            }
+            new_struct.push_field(fld);
+        } else {
+            new_struct.field(&format!("pub {}", rs_name), format!("Option<{}>", foo.rs_type));
+        }
+    }
+    if serializable {
+        new_struct.derive("Serialize");
+        new_struct.derive("Deserialize");
+        new_struct.r#macro("#[serde(default)]");
+    }
+
+    // Update function
+    // The key goes first, then the values of the columns that were provided
+    let mut new_func = gen_method(options, "update");
+    new_func.arg_ref_self();
+    new_func.arg("db", gen_client_type(options));
+    gen_key_args(&mut new_func, database, &table.primary_key)?;
+    new_func.ret(format!("PgResult<{}>", table.rs_name));
+    new_func.line("let mut assignments: Vec<String> = Vec::new();");
+    new_func.line(format!("let mut values: Vec<{}> = vec![{}];", gen_param_type(options), gen_key_values(&table.primary_key)));
+    for field in &fields {
+        let mut block = codegen::Block::new(&format!("if let Some(value) = &self.{}", gen_fld_name(&field.name)));
+        block.line("values.push(value);");
+        block.line(format!("assignments.push(format!({:?}, values.len()));", format!("{} = ${{}}", quote_ident(&field.name))));
+        new_func.push_block(block);
+    }
+
+    // Without any column to update, the row is returned as is
+    let key_filter = gen_key_filter(&table.primary_key, 1);
+    let select_sql = format!("SELECT * FROM {} WHERE {}", table.sql_name, key_filter);
+    let update_sql = format!("UPDATE {} SET {{}} WHERE {} RETURNING *", table.sql_name, key_filter);
+    new_func.line(format!(
+        "let sql = if assignments.is_empty() {{ {:?}.to_string() }} else {{ format!({:?}, assignments.join(\", \")) }};",
+        select_sql, update_sql
+    ));
+    new_func.line(format!("let query = db.query(sql.as_str(), &values){}?;", gen_await(options)));
+    new_func.line("let row = query.into_iter().next().ok_or(PgError::KeyNotFound)?;");
+    new_func.line(format!("Ok({}::from_postgres_row(row)?)", table.rs_name));
+
+    // Done
+    let new_impl = scope.new_impl(&struct_name);
+    new_impl.push_fn(new_func);
+    Ok(())
+}
+
+/// Columns that a patch can update, every writable one but the key
+///
+fn gen_patch_fields<'a>(table: &Table<'a>) -> Vec<&'a PgField> {
+    table
+        .fields
+        .iter()
+        .filter(|f| is_writable(f))
+        .filter(|f| !table.primary_key.iter().any(|k| k.name == f.name))
+        .collect()
+}
+
+/// Whether every column can be serialized
+///
+fn is_serializable(database: &BTreeMap<Oid, PgType>, fields: &[&PgField]) -> Result<bool, String> {
+    let serializable = fields
+        .iter()
+        .map(|f| resolve_column_type(database, f).map(|t| t.serializable))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .all(|x| x);
+    Ok(serializable)
+}
+
+/// Get a row by its primary key
+///
+fn gen_get_by_pk(
+    names: &mut BTreeSet<String>,
+    database: &BTreeMap<Oid, PgType>,
+    options: &Options,
+    table: &Table,
+) -> Result<codegen::Function, String> {
+    let mut new_func = gen_table_method(names, options, "get_by_pk");
+    new_func.arg("db", gen_client_type(options));
+    gen_key_args(&mut new_func, database, &table.primary_key)?;
+    new_func.ret("PgResult<Self>");
+
+    let sql = format!("SELECT * FROM {} WHERE {}", table.sql_name, gen_key_filter(&table.primary_key, 1));
+    gen_single_row(&mut new_func, options, &sql, &gen_key_values(&table.primary_key));
+    Ok(new_func)
+}
+
+/// Overwrite every column of a row but the primary key, and get it back as stored in the database
+/// Tables where all columns are part of the key can't be updated
+///
+fn gen_update_by_pk(names: &mut BTreeSet<String>, options: &Options, table: &Table) -> Result<Option<codegen::Function>, String> {
+    let values = table
+        .fields
+        .iter()
+        .filter(|f| is_writable(f))
+        .filter(|f| !table.primary_key.iter().any(|k| k.name == f.name))
+        .collect::<Vec<_>>();
+    if values.is_empty() {
+        return Ok(None);
+    }
+
+    let mut new_func = gen_table_method(names, options, "update_by_pk");
+    new_func.arg_ref_self();
+    new_func.arg("db", gen_client_type(options));
+    new_func.ret("PgResult<Self>");
+
+    // The key goes first, then the new values
+    let assignments = values
+        .iter()
+        .enumerate()
+        .map(|(x, f)| format!("{} = ${}", quote_ident(&f.name), x + table.primary_key.len() + 1))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let sql = format!("UPDATE {} SET {} WHERE {} RETURNING *", table.sql_name, assignments, gen_key_filter(&table.primary_key, 1));
+    let rs_args = format!("{}, {}", gen_self_args(&table.primary_key), gen_self_args(&values));
+    gen_single_row(&mut new_func, options, &sql, &rs_args);
+    Ok(Some(new_func))
+}
+
+/// Delete a row by its primary key, and get it back
+///
+fn gen_delete_by_pk(
+    names: &mut BTreeSet<String>,
+    database: &BTreeMap<Oid, PgType>,
+    options: &Options,
+    table: &Table,
+) -> Result<codegen::Function, String> {
+    let mut new_func = gen_table_method(names, options, "delete_by_pk");
+    new_func.arg("db", gen_client_type(options));
+    gen_key_args(&mut new_func, database, &table.primary_key)?;
+    new_func.ret("PgResult<Self>");
+
+    let sql = format!("DELETE FROM {} WHERE {} RETURNING *", table.sql_name, gen_key_filter(&table.primary_key, 1));
+    gen_single_row(&mut new_func, options, &sql, &gen_key_values(&table.primary_key));
+    Ok(new_func)
+}
+
+/// Whether other tables inherit from a table
+///
+fn has_children(database: &BTreeMap<Oid, PgType>, table: &Table) -> bool {
+    database
+        .values()
+        .any(|t| matches!(t, PgType::Composite { parents, .. } if parents.contains(&table.oid)))
+}
+
+/// Get every row of a table, either including the rows of the tables that inherit from it, or only its own rows
+///
+fn gen_select_all(names: &mut BTreeSet<String>, options: &Options, table: &Table, only: bool) -> codegen::Function {
+    let mut new_func = gen_table_method(names, options, if only { "select_only" } else { "select_all" });
+    new_func.arg("db", gen_client_type(options));
+
+    let sql = format!("SELECT * FROM {}{}", if only { "ONLY " } else { "" }, table.sql_name);
+    gen_all_rows(&mut new_func, options, &sql, "", "Self");
+    new_func
+}
+
+/// Convert a row of a child table into a row of its parent, by leaving out the columns added by the child
+/// Inherited columns may be NOT NULL in the child while still nullable in the parent, and they may reference a lookup
+/// table only in the child, since foreign keys aren't inherited
+///
+fn gen_parent_conversion(scope: &mut codegen::Scope, database: &BTreeMap<Oid, PgType>, child: &Table, parent: &Table) -> Result<(), String> {
+    let mut block = codegen::Block::new("Self");
+    for parent_field in parent.fields {
+        let field = child.columns(&[parent_field.name.clone()])?[0];
+        let rs_name = gen_fld_name(&field.name);
+        let value = match (field.lookup, parent_field.lookup) {
+            (child_lookup, parent_lookup) if child_lookup == parent_lookup => format!("row.{}", rs_name),
+            (Some(_), None) => {
+                let rs_key = resolve_fld_type(database, parent_field.typ)?.rs_type;
+                if field.is_nullable {
+                    format!("row.{}.map(|x| x as {})", rs_name, rs_key)
+                } else {
+                    format!("row.{} as {}", rs_name, rs_key)
+                }
+            }
+            _ => return Err(format!("Column {} of {} references another lookup table than in {}", field.name, child.sql_name, parent.sql_name)),
+        };
+        if parent_field.is_nullable && !field.is_nullable {
+            block.line(format!("{}: Some({}),", rs_name, value));
+        } else {
+            block.line(format!("{}: {},", rs_name, value));
+        }
+    }
+
+    // The impl is only added once every column can be converted
+    let new_impl = scope.new_impl(&parent.rs_name);
+    new_impl.impl_trait(format!("From<{}>", child.rs_name));
+    let new_func = new_impl.new_fn("from");
+    new_func.arg("row", &child.rs_name);
+    new_func.ret("Self");
+    new_func.push_block(block);
+    Ok(())
+}
+
+/// Insert many rows at once with a binary COPY, and get the number of rows inserted
+/// Every column is copied as is, since COPY can't fall back to the default value of a column for some rows only;
+/// the keys can be taken beforehand from their sequence
+///
+fn gen_copy_in(
+    names: &mut BTreeSet<String>,
+    database: &BTreeMap<Oid, PgType>,
+    options: &Options,
+    table: &Table,
+) -> Result<codegen::Function, String> {
+    let mut new_func = gen_table_method(names, options, "copy_in");
+    new_func.arg("db", gen_client_type(options));
+    new_func.arg("rows", "impl IntoIterator<Item = Self>");
+    new_func.ret("PgResult<u64>");
+
+    let fields = table.fields.iter().filter(|f| !f.is_generated).collect::<Vec<_>>();
+    let columns = fields.iter().map(|f| quote_ident(&f.name)).collect::<Vec<_>>().join(", ");
+    let values = fields
+        .iter()
+        .map(|f| format!("&row.{}", gen_fld_name(&f.name)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    gen_copy_types(&mut new_func, database, options, &fields)?;
+    let sql = format!("COPY {} ({}) FROM STDIN (FORMAT binary)", table.sql_name, columns);
+    let mut block = codegen::Block::new("for row in rows");
+    match options.flavor {
+        Flavor::Sync => {
+            new_func.line(format!("let sink = db.copy_in({:?})?;", sql));
+            new_func.line("let mut writer = postgres::binary_copy::BinaryCopyInWriter::new(sink, &types);");
+            block.line(format!("writer.write(&[{}])?;", values));
+            new_func.push_block(block);
+            new_func.line("Ok(writer.finish()?)");
+        }
+        Flavor::Async => {
+            new_func.line(format!("let sink = db.client().copy_in({:?}).await?;", sql));
+            new_func.line("let writer = tokio_postgres::binary_copy::BinaryCopyInWriter::new(sink, &types);");
+            new_func.line("futures_util::pin_mut!(writer);");
+            block.line(format!("writer.as_mut().write(&[{}]).await?;", values));
+            new_func.push_block(block);
+            new_func.line("Ok(writer.finish().await?)");
+        }
+    }
+    Ok(new_func)
+}
+
+/// Read every row with a binary COPY, which is faster than a query for large tables
+/// The rows are decoded as they arrive, so the iterator borrows the connection until it's dropped
+///
+fn gen_copy_out(
+    names: &mut BTreeSet<String>,
+    database: &BTreeMap<Oid, PgType>,
+    options: &Options,
+    table: &Table,
+) -> Result<codegen::Function, String> {
+    let mut new_func = gen_table_method(names, options, "copy_out");
+    let fields = table.fields.iter().collect::<Vec<_>>();
+    let columns = fields.iter().map(|f| quote_ident(&f.name)).collect::<Vec<_>>().join(", ");
+    let select = format!("SELECT {} FROM {}", columns, table.sql_name);
+    let sql = format!("COPY ({}) TO STDOUT (FORMAT binary)", select);
+    let decoder = match options.flavor {
+        Flavor::Sync => {
+            new_func.generic("'a");
+            new_func.arg("db", format!("&'a mut {}", gen_client_trait(options)));
+            new_func.ret("PgResult<impl Iterator<Item = PgResult<Self>> + 'a>");
+            gen_copy_types(&mut new_func, database, options, &fields)?;
+            new_func.line(format!("let reader = db.copy_out({:?})?;", sql));
+            new_func.line("let rows = postgres::binary_copy::BinaryCopyOutIter::new(reader, &types);");
+            "Ok(postgres::fallible_iterator::FallibleIterator::iterator(rows).map(|row|"
+        }
+        Flavor::Async => {
+            new_func.arg("db", gen_client_type(options));
+            new_func.ret("PgResult<impl futures_util::Stream<Item = PgResult<Self>>>");
+            gen_copy_types(&mut new_func, database, options, &fields)?;
+            new_func.line(format!("let stream = db.client().copy_out({:?}).await?;", sql));
+            new_func.line("let rows = tokio_postgres::binary_copy::BinaryCopyOutStream::new(stream, &types);");
+            "Ok(futures_util::StreamExt::map(rows, |row|"
+        }
+    };
+    let mut block = codegen::Block::new(decoder);
+    block.line("let row = row?;");
+    let mut row_block = codegen::Block::new("Ok(Self");
+    for (x, field) in table.fields.iter().enumerate() {
+        row_block.line(format!("{}: row.try_get({})?,", gen_fld_name(&field.name), x));
+    }
+    row_block.after(")");
+    block.push_block(row_block);
+    block.after("))");
+    new_func.push_block(block);
+    Ok(new_func)
+}
+
+/// List the types of some columns, which the binary format needs
+///
+fn gen_copy_types(new_func: &mut codegen::Function, database: &BTreeMap<Oid, PgType>, options: &Options, fields: &[&PgField]) -> Result<(), String> {
+    let types = fields
+        .iter()
+        .map(|f| gen_type_value(database, options, f.typ))
+        .collect::<Result<Vec<_>, _>>()?;
+    new_func.line(format!("let types = [{}];", types.join(", ")));
+    Ok(())
+}
+
+/// Refresh a materialized view
+/// Concurrent refreshes don't block the readers, but they need a unique index over plain columns of every row;
+/// without one, the generated function panics when asked for one, as it's a mistake of the caller
+///
+fn gen_refresh(options: &Options, table: &Table) -> codegen::Function {
+    let mut new_func = codegen::Function::new(&format!("refresh_{}", gen_function_name(table.schema, table.name)));
+    new_func.vis("pub");
+    new_func.set_async(options.flavor == Flavor::Async);
+    new_func.arg("db", gen_client_type(options));
+    new_func.arg("concurrently", "bool");
+    new_func.ret("PgResult<()>");
+
+    let sql = format!("REFRESH MATERIALIZED VIEW {}", table.sql_name);
+    if table.indexes.iter().any(|i| i.is_unique && !i.is_partial && !i.has_expressions) {
+        let concurrent_sql = format!("REFRESH MATERIALIZED VIEW CONCURRENTLY {}", table.sql_name);
+        new_func.line(format!("let sql = if concurrently {{ {:?} }} else {{ {:?} }};", concurrent_sql, sql));
+        new_func.line(format!("db.execute(sql, &[]){}?;", gen_await(options)));
+    } else {
+        eprintln!("{} has no unique index on plain columns, it can't be refreshed concurrently", table.sql_name);
+        let message = format!("{} has no unique index, it can't be refreshed concurrently", table.sql_name);
+        new_func.line(format!("assert!(!concurrently, \"{{}}\", {:?});", message));
+        new_func.line(format!("db.execute({:?}, &[]){}?;", sql, gen_await(options)));
+    }
+    new_func.line("Ok(())");
+    new_func
+}
+
+/// Whether a column can be given a value, as opposed to the ones always computed by the database
+///
+fn is_writable(field: &PgField) -> bool {
+    !field.is_generated && field.identity != PgIdentity::Always
+}
+
+/// Whether the database can fill a column when it's not provided
+///
+fn has_default(field: &PgField) -> bool {
+    field.has_default || field.identity == PgIdentity::ByDefault
+}
+
+/// Find rows by the leading columns of every btree index, so the lookups never need a sequential scan
+/// When the columns are the whole key of a unique index there is at most one row
+///
+fn gen_find_by(
+    names: &mut BTreeSet<String>,
+    database: &BTreeMap<Oid, PgType>,
+    options: &Options,
+    table: &Table,
+) -> Result<Vec<codegen::Function>, String> {
+    // Different indexes may share some of their leading columns
+    let mut lookups: BTreeMap<Vec<String>, bool> = BTreeMap::new();
+    for index in table.indexes.iter().filter(|i| i.method == "btree" && !i.is_partial) {
+        for len in 1..=index.columns.len() {
+            let is_unique = index.is_unique && !index.has_expressions && len == index.columns.len();
+            let entry = lookups.entry(index.columns[..len].to_vec()).or_insert(false);
+            *entry = *entry || is_unique;
+        }
+    }
+
+    let mut functions = Vec::new();
+    for (columns, is_unique) in lookups {
+        let key = table.columns(&columns)?;
+        let func_name = format!("find_by_{}", key.iter().map(|f| gen_fld_name(&f.name)).collect::<Vec<_>>().join("_and_"));
+        let mut new_func = gen_table_method(names, options, &func_name);
+        new_func.arg("db", gen_client_type(options));
+        gen_key_args(&mut new_func, database, &key)?;
+
+        let sql = format!("SELECT * FROM {} WHERE {}", table.sql_name, gen_key_filter(&key, 1));
+        if is_unique {
+            gen_optional_row(&mut new_func, options, &sql, &gen_key_values(&key), "Self");
+        } else {
+            gen_all_rows(&mut new_func, options, &sql, &gen_key_values(&key), "Self");
+        }
+        functions.push(new_func);
+    }
+    Ok(functions)
+}
+
+/// Keyset pagination over the primary key and every unique index, which gives stable pages even when rows are inserted
+/// Keys with nullable columns are left out, since NULL can't be compared
+///
+fn gen_page_by(
+    names: &mut BTreeSet<String>,
+    database: &BTreeMap<Oid, PgType>,
+    options: &Options,
+    table: &Table,
+) -> Result<Vec<codegen::Function>, String> {
+    let mut keys = BTreeSet::new();
+    if !table.primary_key.is_empty() {
+        keys.insert(table.primary_key.iter().map(|f| f.name.clone()).collect::<Vec<_>>());
+    }
+    for index in table
+        .indexes
+        .iter()
+        .filter(|i| i.method == "btree" && i.is_unique && !i.is_partial && !i.has_expressions)
+    {
+        keys.insert(index.columns.clone());
+    }
+
+    let mut functions = Vec::new();
+    for columns in keys {
+        let key = table.columns(&columns)?;
+        if key.iter().any(|f| f.is_nullable) {
+            continue;
+        }
+
+        // Multi-column keys are passed as tuples
+        let key_types = key.iter().map(|f| resolve_column_arg_type(database, f)).collect::<Result<Vec<_>, _>>()?;
+        let key_names = key.iter().map(|f| gen_fld_name(&f.name)).collect::<Vec<_>>();
+        let (key_type, key_pattern) = if key.len() == 1 {
+            (key_types[0].clone(), key_names[0].clone())
+        } else {
+            (format!("({})", key_types.join(", ")), format!("({})", key_names.join(", ")))
+        };
+
+        let func_name = format!("page_by_{}", key_names.join("_and_"));
+        let mut new_func = gen_table_method(names, options, &func_name);
+        new_func.arg("db", gen_client_type(options));
+        new_func.arg("after", format!("Option<{}>", key_type));
+        new_func.arg("limit", "i64");
+        new_func.arg("descending", "bool");
+        new_func.ret("PgResult<Vec<Self>>");
+
+        // Row comparisons work for any number of columns, as long as all of them are sorted the same way
+        let sql_columns = key.iter().map(|f| quote_ident(&f.name)).collect::<Vec<_>>().join(", ");
+        let sql_values = (0..key.len()).map(|x| format!("${}", x + 1)).collect::<Vec<_>>().join(", ");
+        let mut block = codegen::Block::new("let query = match (&after, descending)");
+        for (descending, operator, direction) in &[(false, ">", "ASC"), (true, "<", "DESC")] {
+            let order = key
+                .iter()
+                .map(|f| format!("{} {}", quote_ident(&f.name), direction))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let sql = format!("SELECT * FROM {} ORDER BY {} LIMIT $1", table.sql_name, order);
+            block.line(format!("(None, {}) => db.query({:?}, &[&limit]){}?,", descending, sql, gen_await(options)));
+            let sql = format!(
+                "SELECT * FROM {} WHERE ({}) {} ({}) ORDER BY {} LIMIT ${}",
+                table.sql_name,
+                sql_columns,
+                operator,
+                sql_values,
+                order,
+                key.len() + 1
+            );
+            block.line(format!(
+                "(Some({}), {}) => db.query({:?}, &[{}, &limit]){}?,",
+                key_pattern,
+                descending,
+                sql,
+                key_names.join(", "),
+                gen_await(options)
+            ));
+        }
+        block.after(";");
+        new_func.push_block(block);
+        new_func.line("let result: Result<Vec<Self>, _> = query.into_iter().map(Self::from_postgres_row).collect();");
+        new_func.line("Ok(result?)");
+        functions.push(new_func);
+    }
+    Ok(functions)
+}
+
+/// Navigate the foreign keys of a table in both directions, and through the join tables of many-to-many relations
+///
+fn gen_relations(
+    names: &mut BTreeSet<String>,
+    database: &BTreeMap<Oid, PgType>,
+    options: &Options,
+    table: &Table,
+) -> Result<Vec<codegen::Function>, String> {
+    let tables = database
+        .iter()
+        .map(|(oid, type_def)| Table::new(*oid, type_def))
+        .filter_map(Result::transpose)
+        .collect::<Result<Vec<_>, _>>()?;
+    let find_table = |oid: Oid| tables.iter().find(|t| t.oid == oid);
+
+    // The names of the other methods are taken too, so the relations get a suffix when they clash
+    let mut functions = Vec::new();
+
+    // Parent rows, named after the referencing column or the referenced table
+    for fk in table.foreign_keys {
+        let parent = match find_table(fk.ref_type) {
+            Some(x) => x,
+            None => continue,
+        };
+        let columns = table.columns(&fk.columns)?;
+        let ref_columns = parent.columns(&fk.ref_columns)?;
+        let func_name = match columns.as_slice() {
+            [column] if column.name.len() > 3 && column.name.ends_with("_id") => gen_fld_name(&column.name[..column.name.len() - 3].to_string()),
+            _ => gen_function_name(parent.schema, parent.name),
+        };
+        let func_name = gen_unique_name(names, func_name, &fk.name);
+        let mut new_func = gen_method(options, &func_name);
+        new_func.arg_ref_self();
+        new_func.arg("db", gen_client_type(options));
+
+        // A nullable foreign key may reference nothing
+        let sql = format!("SELECT * FROM {} WHERE {}", parent.sql_name, gen_key_filter(&ref_columns, 1));
+        if columns.iter().any(|f| f.is_nullable) {
+            gen_optional_row(&mut new_func, options, &sql, &gen_self_args(&columns), &parent.rs_name);
+        } else {
+            new_func.ret(format!("PgResult<{}>", parent.rs_name));
+            new_func.line(format!("let query = db.query({:?}, &[{}]){}?;", sql, gen_self_args(&columns), gen_await(options)));
+            new_func.line("let row = query.into_iter().next().ok_or(PgError::KeyNotFound)?;");
+            new_func.line(format!("Ok({}::from_postgres_row(row)?)", parent.rs_name));
+        }
+        functions.push(new_func);
+    }
+
+    // Child rows, named after the referencing table
+    for child in &tables {
+        let fks = child.foreign_keys.iter().filter(|fk| fk.ref_type == table.oid).collect::<Vec<_>>();
+        for fk in &fks {
+            let columns = child.columns(&fk.columns)?;
+            let ref_columns = table.columns(&fk.ref_columns)?;
+            let mut func_name = gen_plural_name(&gen_function_name(child.schema, child.name));
+            if fks.len() > 1 {
+                let suffix = columns.iter().map(|f| gen_fld_name(&f.name)).collect::<Vec<_>>().join("_and_");
+                func_name = format!("{}_by_{}", func_name, suffix);
+            }
+            let func_name = gen_unique_name(names, func_name, &fk.name);
+            let mut new_func = gen_method(options, &func_name);
+            new_func.arg_ref_self();
+            new_func.arg("db", gen_client_type(options));
+
+            let sql = format!("SELECT * FROM {} WHERE {}", child.sql_name, gen_key_filter(&columns, 1));
+            gen_all_rows(&mut new_func, options, &sql, &gen_self_args(&ref_columns), &child.rs_name);
+            functions.push(new_func);
+        }
+    }
+
+    // Rows related through a join table, which has exactly two foreign keys and no other data
+    for join in tables.iter().filter(|t| is_join_table(t)) {
+        let (this_fk, other_fk) = match join.foreign_keys.as_slice() {
+            [a, b] if a.ref_type == table.oid && b.ref_type != table.oid => (a, b),
+            [a, b] if b.ref_type == table.oid && a.ref_type != table.oid => (b, a),
+            _ => continue,
+        };
+        let other = match find_table(other_fk.ref_type) {
+            Some(x) => x,
+            None => continue,
+        };
+        let this_columns = join.columns(&this_fk.columns)?;
+        let ref_columns = table.columns(&this_fk.ref_columns)?;
+        let join_condition = join
+            .columns(&other_fk.columns)?
+            .iter()
+            .zip(other.columns(&other_fk.ref_columns)?)
+            .map(|(j, o)| format!("o.{} = j.{}", quote_ident(&o.name), quote_ident(&j.name)))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        let filter = this_columns
+            .iter()
+            .enumerate()
+            .map(|(x, f)| format!("j.{} = ${}", quote_ident(&f.name), x + 1))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+
+        let func_name = gen_plural_name(&gen_function_name(other.schema, other.name));
+        let via = format!("via_{}", gen_function_name(join.schema, join.name));
+        let func_name = gen_unique_name(names, func_name, &via);
+        let mut new_func = gen_method(options, &func_name);
+        new_func.arg_ref_self();
+        new_func.arg("db", gen_client_type(options));
+
+        let sql = format!("SELECT o.* FROM {} o JOIN {} j ON {} WHERE {}", other.sql_name, join.sql_name, join_condition, filter);
+        gen_all_rows(&mut new_func, options, &sql, &gen_self_args(&ref_columns), &other.rs_name);
+        functions.push(new_func);
+    }
+
+    Ok(functions)
+}
+
+/// Walk the trees of tables with a foreign key to themselves, with recursive queries
+/// Every row comes with its distance to the starting row, the starting row itself is at depth 0
+///
+fn gen_tree(
+    names: &mut BTreeSet<String>,
+    database: &BTreeMap<Oid, PgType>,
+    options: &Options,
+    table: &Table,
+) -> Result<Vec<codegen::Function>, String> {
+    let fks = table.foreign_keys.iter().filter(|fk| fk.ref_type == table.oid).collect::<Vec<_>>();
+    let mut functions = Vec::new();
+    for fk in &fks {
+        let columns = table.columns(&fk.columns)?;
+        let ref_columns = table.columns(&fk.ref_columns)?;
+        let suffix = if fks.len() > 1 {
+            format!("_by_{}", columns.iter().map(|f| gen_fld_name(&f.name)).collect::<Vec<_>>().join("_and_"))
+        } else {
+            String::new()
+        };
+
+        // Conditions to go up or down one level, from the rows already found in `tree`
+        // The keys of the rows already found are kept in `__path`, so a cycle in the data ends the recursion
+        let join_up = gen_tree_join(&ref_columns, &columns);
+        let join_down = gen_tree_join(&columns, &ref_columns);
+        let key = format!(
+            "ROW({})",
+            ref_columns
+                .iter()
+                .map(|f| format!("t.{}", quote_ident(&f.name)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        let start = format!(
+            "SELECT t.*, 0 AS \"__depth\", ARRAY[{}] AS \"__path\" FROM {} t WHERE {}",
+            key,
+            table.sql_name,
+            ref_columns
+                .iter()
+                .enumerate()
+                .map(|(x, f)| format!("t.{} = ${}", quote_ident(&f.name), x + 1))
+                .collect::<Vec<_>>()
+                .join(" AND ")
+        );
+        let step = |join: &str| {
+            format!(
+                "SELECT t.*, tree.\"__depth\" + 1, tree.\"__path\" || {} FROM {} t JOIN tree ON {} WHERE NOT {} = ANY(tree.\"__path\")",
+                key, table.sql_name, join, key
+            )
+        };
+        let up = format!("WITH RECURSIVE tree AS ({} UNION ALL {}) ", start, step(&join_up));
+        let select = format!("SELECT {}, \"__depth\" FROM tree", table.fields.iter().map(|f| quote_ident(&f.name)).collect::<Vec<_>>().join(", "));
+
+        // Parents up to the root, nearest first
+        let mut new_func = gen_table_method(names, options, &format!("ancestors{}", suffix));
+        new_func.arg("db", gen_client_type(options));
+        gen_key_args(&mut new_func, database, &ref_columns)?;
+        let sql = format!("{}{} WHERE \"__depth\" > 0 ORDER BY \"__depth\"", up, select);
+        gen_tree_rows(&mut new_func, options, &sql, &gen_key_values(&ref_columns));
+        functions.push(new_func);
+
+        // The same, starting with the row itself
+        let mut new_func = gen_table_method(names, options, &format!("path_to_root{}", suffix));
+        new_func.arg("db", gen_client_type(options));
+        gen_key_args(&mut new_func, database, &ref_columns)?;
+        let sql = format!("{}{} ORDER BY \"__depth\"", up, select);
+        gen_tree_rows(&mut new_func, options, &sql, &gen_key_values(&ref_columns));
+        functions.push(new_func);
+
+        // Children, grandchildren and so on, down to an optional depth
+        let mut new_func = gen_table_method(names, options, &format!("descendants{}", suffix));
+        new_func.arg("db", gen_client_type(options));
+        gen_key_args(&mut new_func, database, &ref_columns)?;
+        new_func.arg("max_depth", "Option<i32>");
+        let max_depth = ref_columns.len() + 1;
+        let sql = format!(
+            "WITH RECURSIVE tree AS ({} UNION ALL {} AND (${}::int4 IS NULL OR tree.\"__depth\" < ${})) {} WHERE \"__depth\" > 0 ORDER BY \"__depth\"",
+            start,
+            step(&join_down),
+            max_depth,
+            max_depth,
+            select
+        );
+        let rs_args = format!("{}, &max_depth", gen_key_values(&ref_columns));
+        gen_tree_rows(&mut new_func, options, &sql, &rs_args);
+        functions.push(new_func);
+
+        // Rows without a parent, which are always at the top
+        let mut new_func = gen_table_method(names, options, &format!("roots{}", suffix));
+        new_func.arg("db", gen_client_type(options));
+        let filter = columns
+            .iter()
+            .map(|f| format!("{} IS NULL", quote_ident(&f.name)))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        let sql = format!("SELECT *, 0 AS \"__depth\" FROM {} WHERE {}", table.sql_name, filter);
+        gen_tree_rows(&mut new_func, options, &sql, "");
+        functions.push(new_func);
+    }
+    Ok(functions)
+}
+
+/// Generate the condition that matches the rows of `t` to the rows of `tree`
+///
+fn gen_tree_join(t_columns: &[&PgField], tree_columns: &[&PgField]) -> String {
+    t_columns
+        .iter()
+        .zip(tree_columns)
+        .map(|(t, tree)| format!("t.{} = tree.{}", quote_ident(&t.name), quote_ident(&tree.name)))
+        .collect::<Vec<_>>()
+        .join(" AND ")
+}
+
+/// Run a recursive query, and convert its rows to `Self` along with their depth
+///
+fn gen_tree_rows(new_func: &mut codegen::Function, options: &Options, sql: &str, rs_args: &str) {
+    new_func.ret("PgResult<Vec<(Self, i32)>>");
+    new_func.line(format!("let query = db.query({:?}, &[{}]){}?;", sql, rs_args, gen_await(options)));
+    new_func.line("let mut result = Vec::new();");
+    let mut block = codegen::Block::new("for row in query");
+    block.line("let depth: i32 = row.get(\"__depth\");");
+    block.line("result.push((Self::from_postgres_row(row)?, depth));");
+    new_func.push_block(block);
+    new_func.line("Ok(result)");
+}
+
+/// Whether a table only links rows of two other tables, so every column is part of a foreign key or filled by the database
+///
+fn is_join_table(table: &Table) -> bool {
+    table.foreign_keys.len() == 2
+        && table
+            .fields
+            .iter()
+            .all(|f| has_default(f) || !is_writable(f) || table.foreign_keys.iter().any(|fk| fk.columns.contains(&f.name)))
+}
+
+/// Avoid name clashes between methods, by adding a suffix to the repeated names, and then a number if needed
+///
+fn gen_unique_name(names: &mut BTreeSet<String>, name: String, suffix: &str) -> String {
+    let mut unique_name = name.clone();
+    if names.contains(&unique_name) {
+        unique_name = format!("{}_{}", name, gen_fld_name(&suffix.to_string()));
+    }
+    let mut x = 2;
+    while names.contains(&unique_name) {
+        unique_name = format!("{}_{}_{}", name, gen_fld_name(&suffix.to_string()), x);
+        x += 1;
+    }
+    names.insert(unique_name.clone());
+    unique_name
+}
+
+/// Naive plural of a table name, names that already look plural are kept as they are
+///
+fn gen_plural_name(name: &str) -> String {
+    if name.ends_with('s') {
+        name.to_string()
+    } else if name.ends_with('x') || name.ends_with("ch") || name.ends_with("sh") {
+        format!("{}es", name)
+    } else if name.ends_with('y') && !name.ends_with("ay") && !name.ends_with("ey") && !name.ends_with("oy") {
+        format!("{}ies", &name[..name.len() - 1])
+    } else {
+        format!("{}s", name)
+    }
+}
+
+/// Create a new public method, async if needed
+///
+fn gen_method(options: &Options, name: &str) -> codegen::Function {
+    let mut new_func = codegen::Function::new(name);
+    new_func.vis("pub");
+    new_func.set_async(options.flavor == Flavor::Async);
+    new_func
+}
+
+/// Create a new method of the table struct, and take its name
+///
+fn gen_table_method(names: &mut BTreeSet<String>, options: &Options, name: &str) -> codegen::Function {
+    names.insert(name.to_string());
+    gen_method(options, name)
+}
+
+/// Add one argument per key column
+///
+fn gen_key_args(new_func: &mut codegen::Function, database: &BTreeMap<Oid, PgType>, key: &[&PgField]) -> Result<(), String> {
+    for field in key {
+        let arg_type = resolve_column_arg_type(database, field)?;
+        new_func.arg(&gen_fld_name(&field.name), arg_type);
+    }
+    Ok(())
+}
+
+/// Generate the Rust values of the key arguments
+///
+fn gen_key_values(key: &[&PgField]) -> String {
+    key.iter().map(|f| format!("&{}", gen_fld_name(&f.name))).collect::<Vec<_>>().join(", ")
+}
+
+/// Generate the Rust values of some fields of `self`
+///
+fn gen_self_args(fields: &[&PgField]) -> String {
+    fields
+        .iter()
+        .map(|f| format!("&self.{}", gen_fld_name(&f.name)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Generate the condition that matches the key columns, with the placeholders starting at `first`
+///
+fn gen_key_filter(key: &[&PgField], first: usize) -> String {
+    key.iter()
+        .enumerate()
+        .map(|(x, f)| format!("{} = ${}", quote_ident(&f.name), x + first))
+        .collect::<Vec<_>>()
+        .join(" AND ")
+}
+
+/// Run a query returning a single row, and convert it to `Self`
+///
+fn gen_single_row(new_func: &mut codegen::Function, options: &Options, sql: &str, rs_args: &str) {
+    new_func.line(format!("let query = db.query({:?}, &[{}]){}?;", sql, rs_args, gen_await(options)));
+    new_func.line("let row = query.into_iter().next().ok_or(PgError::KeyNotFound)?;");
+    new_func.line("Ok(Self::from_postgres_row(row)?)");
+}
+
+/// Run a query returning at most one row, and convert it to the given type
+///
+fn gen_optional_row(new_func: &mut codegen::Function, options: &Options, sql: &str, rs_args: &str, rs_type: &str) {
+    new_func.ret(format!("PgResult<Option<{}>>", rs_type));
+    new_func.line(format!("let query = db.query({:?}, &[{}]){}?;", sql, rs_args, gen_await(options)));
+    let mut block = codegen::Block::new("match query.into_iter().next()");
+    block.line(format!("Some(row) => Ok(Some({}::from_postgres_row(row)?)),", rs_type));
+    block.line("None => Ok(None),");
+    new_func.push_block(block);
+}
+
+/// Run a query returning any number of rows, and convert them to the given type
+///
+fn gen_all_rows(new_func: &mut codegen::Function, options: &Options, sql: &str, rs_args: &str, rs_type: &str) {
+    new_func.ret(format!("PgResult<Vec<{}>>", rs_type));
+    new_func.line(format!("let query = db.query({:?}, &[{}]){}?;", sql, rs_args, gen_await(options)));
+    new_func.line(format!("let result: Result<Vec<{}>, _> = query.into_iter().map({}::from_postgres_row).collect();", rs_type, rs_type));
+    new_func.line("Ok(result?)");
+}
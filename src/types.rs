@@ -16,6 +16,9 @@ pub struct TypeCorrespondence {
 pub struct PgDatabase {
     pub types: BTreeMap<Oid, PgType>,
     pub functions: BTreeMap<Oid, PgFunction>,
+    pub sequences: BTreeMap<Oid, PgSequence>,
+    // Lookup tables, by their row type
+    pub lookups: BTreeMap<Oid, PgLookup>,
 }
 
 // https://www.postgresql.org/docs/current/datatype-pseudo.html
@@ -24,7 +27,30 @@ pub struct PgDatabase {
 pub enum PgType {
     Base(TypeCorrespondence),
     Enum { schema: String, name: String, values: Vec<String> },
-    Composite { schema: String, name: String, is_table: bool, is_view: bool, fields: Vec<PgField> },
+    Composite {
+        schema: String,
+        name: String,
+        is_table: bool,
+        is_view: bool,
+        is_materialized: bool,
+        // Views only allow the statements they can rewrite to their base table
+        is_insertable: bool,
+        is_updatable: bool,
+        is_deletable: bool,
+        fields: Vec<PgField>,
+        // Names of the primary key columns, in key order, views can declare one with a `@key` annotation
+        primary_key: Vec<String>,
+        indexes: Vec<PgIndex>,
+        foreign_keys: Vec<PgForeignKey>,
+        constraints: Vec<PgConstraint>,
+        partition_key: Option<PgPartitionKey>,
+        // Row types of the tables this one inherits from, in declaration order
+        parents: Vec<Oid>,
+        // Label column of lookup tables, marked with a `@lookup` annotation
+        lookup: Option<String>,
+    },
+    // Partitions are read and written through their parent table
+    Partition { schema: String, name: String, parent: Oid },
     Domain { schema: String, name: String, base_type: Oid },
     Array { schema: String, name: String, base_type: Oid },
     Range { schema: String, name: String, base_type: Oid },
@@ -42,6 +68,84 @@ pub enum PgTypeKind {
     Range,
 }
 
+// Index of a table
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PgIndex {
+    pub name: String,
+    // Name of the constraint implemented by the index, if any
+    pub constraint: Option<String>,
+    // Access method: btree, hash, gist...
+    pub method: String,
+    // Key columns in order, up to the first expression
+    pub columns: Vec<String>,
+    pub is_unique: bool,
+    pub is_primary: bool,
+    pub is_partial: bool,
+    pub has_expressions: bool,
+    // Names of the copies of the index in the partitions of a partitioned table
+    pub partition_names: Vec<String>,
+}
+
+// Rows of a lookup table, turned into an enum over its integer key
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PgLookup {
+    pub schema: String,
+    pub name: String,
+    pub key_type: Oid,
+    pub values: Vec<PgLookupValue>,
+}
+
+// Row of a lookup table
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PgLookupValue {
+    pub key: i64,
+    pub label: String,
+}
+
+// How the rows of a partitioned table are split among its partitions
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PgPartitionKey {
+    // Either 'hash', 'list' or 'range'
+    pub strategy: String,
+    // Key columns, up to the first expression
+    pub columns: Vec<String>,
+    pub has_expressions: bool,
+}
+
+// Foreign key of a table
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PgForeignKey {
+    pub name: String,
+    pub columns: Vec<String>,
+    // Row type of the referenced table, and the referenced columns in the same order
+    pub ref_type: Oid,
+    pub ref_columns: Vec<String>,
+}
+
+// Constraint of a table, by name
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PgConstraint {
+    pub name: String,
+    pub kind: PgConstraintKind,
+    // Names of the indexes that implement the constraint in the partitions of a partitioned table
+    pub partition_names: Vec<String>,
+}
+
+/// Kinds of table constraints, NOT NULL ones are left out since their violations don't report a name
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+pub enum PgConstraintKind {
+    #[serde(rename = "p")]
+    PrimaryKey,
+    #[serde(rename = "u")]
+    Unique,
+    #[serde(rename = "f")]
+    ForeignKey,
+    #[serde(rename = "c")]
+    Check,
+    #[serde(rename = "x")]
+    Exclusion,
+}
+
 // Field of a table or a composite type
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct PgField {
@@ -50,6 +154,33 @@ pub struct PgField {
     pub typ: Oid,
     pub is_nullable: bool,
     pub description: Option<String>,
+    // The database fills these columns when they are not provided
+    pub has_default: bool,
+    pub identity: PgIdentity,
+    pub is_generated: bool,
+    // Row type of the lookup table whose enum is used instead of the integer key
+    #[serde(default)]
+    pub lookup: Option<Oid>,
+}
+
+/// Identity columns
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+pub enum PgIdentity {
+    #[serde(rename = "")]
+    None,
+    #[serde(rename = "a")]
+    Always,
+    #[serde(rename = "d")]
+    ByDefault,
+}
+
+// Sequence, its values are of an integer type
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PgSequence {
+    pub schema: String,
+    pub name: String,
+    pub typ: Oid,
+    pub sql_type: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -68,6 +199,8 @@ pub enum PgReturn {
     Void,
     Scalar(Oid),
     Record(Vec<PgArgument>),
+    // A refcursor, along with the type of the rows it yields
+    Cursor(Oid),
 }
 
 // Argument of a function
@@ -75,6 +208,8 @@ pub enum PgReturn {
 pub struct PgArgument {
     pub name: String,
     pub typ: Oid,
+    // SQL name of the type, used to cast the argument in the calls
+    pub sql_type: String,
     pub is_variadic: bool,
     pub is_nullable: bool,
 }
@@ -97,3 +232,13 @@ pub enum PgProcedureKind {
     Aggregate,
     Window,
 }
+
+// --------------------------------------------------------------------------------------------------------------------
+// Functions
+// --------------------------------------------------------------------------------------------------------------------
+
+/// Quote a PostgreSQL identifier, to be used both in the introspection queries and in the generated code
+///
+pub fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}